@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+
+use crate::arcana::{Game, GameChart, GameMusic, PlayStyle};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Music {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub artist: String,
+    pub title: String,
+}
+
+impl GameMusic for Music {
+    const GAME: Game = Game::Ddr;
+    type Chart = Chart;
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    fn artist(&self) -> &str {
+        &self.artist
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Chart {
+    #[serde(rename = "_id")]
+    pub id: String,
+    /// Difficulty as named by Arcana, e.g. `EXPERT`
+    pub difficulty: String,
+    pub music_id: String,
+    pub play_style: PlayStyle,
+    pub rating: u32,
+}
+
+impl GameChart for Chart {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+
+use crate::arcana::{Game, GameProfile};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Profile {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub name: String,
+    pub ddr_id: String,
+}
+
+impl GameProfile for Profile {
+    const GAME: Game = Game::Ddr;
+    const NAME_FIELD: &'static str = "name";
+    const ID_FIELD: &'static str = "ddr_id";
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn game_id(&self) -> &str {
+        &self.ddr_id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arcana::{find_profiles, mock::MockArcana};
+
+    #[tokio::test]
+    async fn test_find_profiles() {
+        let mut arcana = MockArcana::new().await;
+        arcana
+            .fixture(
+                "/ddr/19/profiles/",
+                &[("where", r#"{"ddr_id":"5129-4410"}"#)],
+                "ddr_profiles",
+            )
+            .await;
+        arcana
+            .fixture(
+                "/ddr/19/profiles/",
+                &[("where", r#"{"name":"5129-4410"}"#)],
+                "empty",
+            )
+            .await;
+
+        // falls back to the DDR ID when no name matches
+        let profiles: Vec<Profile> = find_profiles(&arcana.client(), 19, "5129-4410")
+            .await
+            .unwrap();
+        assert_eq!(profiles.len(), 1);
+        assert_eq!(profiles[0].name, "ORIGIN");
+    }
+}
@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+use crate::arcana::GameScore;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ScoreHistory {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub chart_id: String,
+    pub ex_score: Option<u32>,
+    /// Clear lamp as named by Arcana, e.g. `GREAT_FULL_COMBO`
+    pub lamp: String,
+    pub music_id: String,
+    pub profile_id: String,
+    pub score: u32,
+    pub timestamp: String,
+}
+
+impl GameScore for ScoreHistory {
+    fn music_id(&self) -> &str {
+        &self.music_id
+    }
+
+    fn chart_id(&self) -> &str {
+        &self.chart_id
+    }
+}
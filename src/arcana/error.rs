@@ -0,0 +1,78 @@
+use reqwest::StatusCode;
+use std::{error::Error, fmt};
+
+/// Errors of Arcana API requests
+#[derive(Debug)]
+pub enum ArcanaError {
+    /// The token is missing or invalid
+    Unauthorized,
+    /// The resource or game version does not exist
+    NotFound,
+    RateLimited,
+    /// Arcana responded with a server error
+    Unavailable(StatusCode),
+    /// Arcana responded with an unexpected status
+    Status(StatusCode),
+    /// The request could not be sent or the response could not be read
+    Request(reqwest::Error),
+    InvalidUrl(String),
+    /// The response body is not what we expected
+    Decode {
+        error: serde_json::Error,
+        body: String,
+    },
+}
+
+/// Only the beginning of a malformed body is shown in error messages
+const BODY_PREVIEW: usize = 200;
+
+impl fmt::Display for ArcanaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArcanaError::Unauthorized => write!(f, "unauthorized by Arcana"),
+            ArcanaError::NotFound => write!(f, "not found on Arcana"),
+            ArcanaError::RateLimited => write!(f, "rate limited by Arcana"),
+            ArcanaError::Unavailable(status) => write!(f, "Arcana is unavailable ({})", status),
+            ArcanaError::Status(status) => write!(f, "unexpected Arcana status {}", status),
+            ArcanaError::Request(e) => write!(f, "Arcana request failed: {}", e),
+            ArcanaError::InvalidUrl(e) => write!(f, "invalid Arcana URL: {}", e),
+            ArcanaError::Decode { error, body } => write!(
+                f,
+                "failed to decode Arcana response: {}, body: {}",
+                error,
+                body.chars().take(BODY_PREVIEW).collect::<String>()
+            ),
+        }
+    }
+}
+
+impl Error for ArcanaError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ArcanaError::Request(e) => Some(e),
+            ArcanaError::Decode { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ArcanaError {
+    fn from(e: reqwest::Error) -> Self {
+        ArcanaError::Request(e)
+    }
+}
+
+impl ArcanaError {
+    /// Map an unsuccessful status to its error
+    pub fn from_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => ArcanaError::Unauthorized,
+            StatusCode::NOT_FOUND => ArcanaError::NotFound,
+            StatusCode::TOO_MANY_REQUESTS => ArcanaError::RateLimited,
+            s if s.is_server_error() => ArcanaError::Unavailable(s),
+            s => ArcanaError::Status(s),
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ArcanaError>;
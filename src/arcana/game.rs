@@ -0,0 +1,212 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::{fmt, str::FromStr};
+
+use super::{ArcanaClient, Query, Result};
+
+/// Largest page Eve serves, so that listing everything takes fewer requests
+/// than with its default page size
+pub const MAX_PAGE_SIZE: u32 = 50;
+
+/// Games served by Arcana
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Game {
+    Iidx,
+    Sdvx,
+    Ddr,
+}
+
+impl Game {
+    /// Path of the game under the API root
+    pub fn path(&self) -> &'static str {
+        match self {
+            Game::Iidx => "iidx",
+            Game::Sdvx => "sdvx",
+            Game::Ddr => "ddr",
+        }
+    }
+}
+
+/// Single or double play, shared by the IIDX and DDR charts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+pub enum PlayStyle {
+    #[serde(rename = "SINGLE")]
+    Single,
+    #[serde(rename = "DOUBLE")]
+    Double,
+}
+
+impl fmt::Display for PlayStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlayStyle::Single => write!(f, "SP"),
+            PlayStyle::Double => write!(f, "DP"),
+        }
+    }
+}
+
+impl FromStr for PlayStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "SP" | "SINGLE" => Ok(PlayStyle::Single),
+            "DP" | "DOUBLE" => Ok(PlayStyle::Double),
+            _ => Err(format!("invalid play style: {}", s)),
+        }
+    }
+}
+
+/// Profile of a player, found by the player name or the ID shown in game
+pub trait GameProfile: DeserializeOwned {
+    const GAME: Game;
+    /// Field of the player name, e.g. `dj_name`
+    const NAME_FIELD: &'static str;
+    /// Field of the ID shown in game, e.g. `iidx_id`
+    const ID_FIELD: &'static str;
+
+    /// Arcana ID of the profile, which plays refer to
+    fn id(&self) -> &str;
+    /// Player name, e.g. the DJ name
+    fn name(&self) -> &str;
+    /// ID shown in game, e.g. the IIDX ID
+    fn game_id(&self) -> &str;
+}
+
+/// Song of a game, searchable by its title and artist
+pub trait GameMusic: DeserializeOwned + Clone {
+    const GAME: Game;
+    type Chart: GameChart;
+
+    fn id(&self) -> &str;
+    fn title(&self) -> &str;
+    fn artist(&self) -> &str;
+}
+
+/// Chart of a song
+pub trait GameChart: DeserializeOwned {
+    fn id(&self) -> &str;
+}
+
+/// Play of a chart recorded in the score history
+pub trait GameScore: DeserializeOwned {
+    fn music_id(&self) -> &str;
+    fn chart_id(&self) -> &str;
+}
+
+/// Collect all items of a list endpoint of the game version matching the
+/// query, up to `limit`
+pub async fn get_items<I: DeserializeOwned>(
+    client: &ArcanaClient,
+    game: Game,
+    version: u32,
+    category: &str,
+    query: &Query,
+    limit: Option<usize>,
+) -> Result<Vec<I>> {
+    client
+        .get_all(
+            &format!("{}/{}/{}", game.path(), version, category),
+            &query.args(),
+            limit,
+        )
+        .await
+}
+
+/// Get the profiles with the player name, or with the in-game ID if there
+/// is none
+pub async fn find_profiles<P: GameProfile>(
+    client: &ArcanaClient,
+    version: u32,
+    param: &str,
+) -> Result<Vec<P>> {
+    for field in [P::NAME_FIELD, P::ID_FIELD] {
+        let profiles = get_items(
+            client,
+            P::GAME,
+            version,
+            "profiles/",
+            &Query::new().eq(field, param),
+            None,
+        )
+        .await?;
+        if !profiles.is_empty() {
+            return Ok(profiles);
+        }
+    }
+    Ok(Vec::new())
+}
+
+/// Get all music of the version
+pub async fn get_catalogue<M: GameMusic>(client: &ArcanaClient, version: u32) -> Result<Vec<M>> {
+    get_items(
+        client,
+        M::GAME,
+        version,
+        "music/",
+        &Query::new().max_results(MAX_PAGE_SIZE),
+        None,
+    )
+    .await
+}
+
+/// Get the items of a list endpoint with any of the IDs, e.g. the music
+/// of the songs in recent plays
+pub async fn get_by_ids<I: DeserializeOwned>(
+    client: &ArcanaClient,
+    game: Game,
+    version: u32,
+    category: &str,
+    ids: &[&str],
+) -> Result<Vec<I>> {
+    get_items(
+        client,
+        game,
+        version,
+        category,
+        &Query::new().one_of("_id", ids.iter().map(|&id| Value::from(id))),
+        None,
+    )
+    .await
+}
+
+/// Get the charts of the song
+pub async fn get_music_charts<M: GameMusic>(
+    client: &ArcanaClient,
+    version: u32,
+    music_id: &str,
+) -> Result<Vec<M::Chart>> {
+    get_items(
+        client,
+        M::GAME,
+        version,
+        "charts/",
+        &Query::new().eq("music_id", music_id),
+        None,
+    )
+    .await
+}
+
+/// Query of all plays of a profile
+pub fn plays(profile_id: &str) -> Query {
+    Query::new().eq("profile_id", profile_id)
+}
+
+/// Get the latest `n` plays matching the query, from the newest to the oldest
+pub async fn get_recent<S: DeserializeOwned>(
+    client: &ArcanaClient,
+    game: Game,
+    version: u32,
+    query: Query,
+    n: u32,
+) -> Result<Vec<S>> {
+    get_items(
+        client,
+        game,
+        version,
+        "score_history/",
+        &query.sort("-timestamp").max_results(n.min(MAX_PAGE_SIZE)),
+        Some(n as usize),
+    )
+    .await
+}
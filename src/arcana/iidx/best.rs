@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+
+use super::{plays, query_score_history, Chart, Lamp, ScoreHistory};
+use crate::arcana::{ArcanaClient, Result};
+
+/// A best value and when it was first set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record<T> {
+    pub value: T,
+    pub timestamp: String,
+}
+
+impl<T> Record<T> {
+    fn new(value: T, play: &ScoreHistory) -> Self {
+        Self {
+            value,
+            timestamp: play.timestamp.clone(),
+        }
+    }
+}
+
+/// Personal bests of a profile on a chart
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Best {
+    pub chart_id: String,
+    pub ex_score: Record<u32>,
+    pub lamp: Record<Lamp>,
+    /// Lowest miss count, missing if no play has recorded one
+    pub miss_count: Option<Record<u32>>,
+}
+
+impl Best {
+    fn new(play: &ScoreHistory) -> Self {
+        Self {
+            chart_id: play.chart_id.clone(),
+            ex_score: Record::new(play.ex_score, play),
+            lamp: Record::new(play.lamp, play),
+            miss_count: play.miss_count.map(|m| Record::new(m, play)),
+        }
+    }
+
+    /// Take the better values of a later play
+    fn update(&mut self, play: &ScoreHistory) {
+        if play.ex_score > self.ex_score.value {
+            self.ex_score = Record::new(play.ex_score, play);
+        }
+        if play.lamp > self.lamp.value {
+            self.lamp = Record::new(play.lamp, play);
+        }
+        if let Some(miss_count) = play.miss_count {
+            if self
+                .miss_count
+                .as_ref()
+                .is_none_or(|m| miss_count < m.value)
+            {
+                self.miss_count = Some(Record::new(miss_count, play));
+            }
+        }
+    }
+}
+
+/// Aggregate plays into the personal bests by chart ID
+pub fn best_by_chart(plays: &[ScoreHistory]) -> HashMap<String, Best> {
+    let mut plays = plays.iter().collect::<Vec<_>>();
+    // go from the oldest so that a record keeps when it was first set
+    plays.sort();
+    let mut bests: HashMap<String, Best> = HashMap::new();
+    for play in plays {
+        match bests.get_mut(&play.chart_id) {
+            Some(best) => best.update(play),
+            None => {
+                bests.insert(play.chart_id.clone(), Best::new(play));
+            }
+        }
+    }
+    bests
+}
+
+/// Best lamp on each of the charts, counting unplayed charts as NO PLAY
+pub fn best_lamps<'a>(
+    charts: &'a [Chart],
+    bests: &HashMap<String, Best>,
+) -> Vec<(&'a Chart, Lamp)> {
+    charts
+        .iter()
+        .map(|c| (c, bests.get(&c.id).map_or(Lamp::NoPlay, |b| b.lamp.value)))
+        .collect()
+}
+
+/// Get the personal bests of a profile on each chart of a song
+pub async fn get_best(
+    client: &ArcanaClient,
+    version: u32,
+    profile_id: &str,
+    music_id: &str,
+) -> Result<HashMap<String, Best>> {
+    let plays = query_score_history(
+        client,
+        version,
+        &plays(profile_id).eq("music_id", music_id),
+        None,
+    )
+    .await?;
+    Ok(best_by_chart(&plays))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(
+        chart_id: &str,
+        ex_score: u32,
+        lamp: Lamp,
+        miss_count: Option<u32>,
+        timestamp: &str,
+    ) -> ScoreHistory {
+        ScoreHistory {
+            id: timestamp.to_owned(),
+            chart_id: chart_id.to_owned(),
+            ex_score,
+            lamp,
+            miss_count,
+            music_id: "G6vGmV2XC2Y".to_owned(),
+            profile_id: "C3PttzgAx6F".to_owned(),
+            raised: false,
+            status: lamp,
+            timestamp: timestamp.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_best_by_chart() {
+        let plays = [
+            play(
+                "8bWq2LkPz0x",
+                2741,
+                Lamp::HardClear,
+                Some(21),
+                "2021-10-03T13:01:52Z",
+            ),
+            play(
+                "8bWq2LkPz0x",
+                2650,
+                Lamp::Failed,
+                None,
+                "2021-09-01T10:00:00Z",
+            ),
+            play(
+                "8bWq2LkPz0x",
+                2741,
+                Lamp::Clear,
+                Some(15),
+                "2021-10-04T09:30:00Z",
+            ),
+            play(
+                "3rN9ctmLu3V",
+                1800,
+                Lamp::FullCombo,
+                Some(0),
+                "2021-09-02T10:00:00Z",
+            ),
+        ];
+        let bests = best_by_chart(&plays);
+        assert_eq!(bests.len(), 2);
+
+        let best = &bests["8bWq2LkPz0x"];
+        assert_eq!(best.ex_score.value, 2741);
+        assert_eq!(best.ex_score.timestamp, "2021-10-03T13:01:52Z");
+        assert_eq!(best.lamp.value, Lamp::HardClear);
+        let miss_count = best.miss_count.as_ref().unwrap();
+        assert_eq!(miss_count.value, 15);
+        assert_eq!(miss_count.timestamp, "2021-10-04T09:30:00Z");
+
+        let charts = [
+            Chart {
+                id: "8bWq2LkPz0x".to_owned(),
+                ..Default::default()
+            },
+            Chart {
+                id: "Qm7rT1vYx5e".to_owned(),
+                ..Default::default()
+            },
+        ];
+        let lamps = best_lamps(&charts, &bests)
+            .into_iter()
+            .map(|(_, lamp)| lamp)
+            .collect::<Vec<_>>();
+        assert_eq!(lamps, vec![Lamp::HardClear, Lamp::NoPlay]);
+
+        assert!(Lamp::FullCombo > Lamp::ExHardClear);
+        assert!(Lamp::AssistClear < Lamp::EasyClear);
+    }
+}
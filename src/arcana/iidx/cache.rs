@@ -0,0 +1,325 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::sync::RwLock;
+
+use super::{
+    get_all_charts, get_all_music, get_charts, get_music, get_profile_using_id, Chart, Music,
+    Profile,
+};
+use crate::{
+    arcana::{ArcanaClient, Result},
+    store::JsonFile,
+};
+
+/// How long profiles are cached, which change with every play unlike the
+/// metadata
+const PROFILE_TTL: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct Entry<T> {
+    value: T,
+    /// Unix timestamp when the value is fetched
+    fetched: i64,
+}
+
+impl<T> Entry<T> {
+    fn new(value: T) -> Self {
+        Self {
+            value,
+            fetched: Utc::now().timestamp(),
+        }
+    }
+
+    fn is_fresh(&self, ttl: Duration) -> bool {
+        Utc::now().timestamp() - self.fetched < ttl.as_secs() as i64
+    }
+}
+
+/// Cached metadata of a game version
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct VersionCache {
+    /// Music by ID
+    music: HashMap<String, Entry<Music>>,
+    /// Charts by music ID
+    charts: HashMap<String, Entry<Vec<Chart>>>,
+    /// Unix timestamp when all music of the version is fetched
+    catalogue: Option<i64>,
+    /// Profiles by IIDX ID, kept in memory only as they go stale quickly
+    #[serde(skip)]
+    profiles: HashMap<String, Entry<Profile>>,
+}
+
+/// Music and chart metadata cache persisted to disk, which also keeps
+/// profiles in memory for a short while
+#[derive(Debug, Clone)]
+pub struct MetadataCache {
+    versions: Arc<RwLock<HashMap<u32, VersionCache>>>,
+    file: JsonFile,
+    /// Whether there are entries not saved yet
+    dirty: Arc<AtomicBool>,
+    ttl: Duration,
+}
+
+impl MetadataCache {
+    /// Load the cache from the path, starting empty if it does not exist
+    pub async fn load(path: impl AsRef<Path>, ttl: Duration) -> Self {
+        let file = JsonFile::new(path);
+        Self {
+            versions: Arc::new(RwLock::new(file.load().await)),
+            file,
+            dirty: Arc::new(AtomicBool::new(false)),
+            ttl,
+        }
+    }
+
+    async fn save(&self) {
+        self.dirty.store(false, Ordering::Relaxed);
+        if let Err(e) = self.file.save(&self.versions).await {
+            log::warn!("Failed to save metadata cache: {}", e);
+        }
+    }
+
+    /// Mark the cache as changed, to be saved by the next `flush`
+    fn touch(&self) {
+        self.dirty.store(true, Ordering::Relaxed);
+    }
+
+    /// Save the entries fetched since the last save, if any
+    pub async fn flush(&self) {
+        if self.dirty.load(Ordering::Relaxed) {
+            self.save().await;
+        }
+    }
+
+    pub async fn music(
+        &self,
+        client: &ArcanaClient,
+        version: u32,
+        id: &str,
+    ) -> Result<Option<Music>> {
+        if let Some(entry) = self
+            .versions
+            .read()
+            .await
+            .get(&version)
+            .and_then(|v| v.music.get(id))
+            .filter(|e| e.is_fresh(self.ttl))
+        {
+            return Ok(Some(entry.value.clone()));
+        }
+
+        let music = get_music(client, version, id).await?;
+        if let Some(m) = &music {
+            self.versions
+                .write()
+                .await
+                .entry(version)
+                .or_default()
+                .music
+                .insert(id.to_owned(), Entry::new(m.clone()));
+            self.touch();
+        }
+        Ok(music)
+    }
+
+    /// Get all music of the version
+    pub async fn catalogue(&self, client: &ArcanaClient, version: u32) -> Result<Vec<Music>> {
+        if let Some(v) = self.versions.read().await.get(&version) {
+            if v.catalogue
+                .is_some_and(|fetched| Utc::now().timestamp() - fetched < self.ttl.as_secs() as i64)
+            {
+                return Ok(v.music.values().map(|e| e.value.clone()).collect());
+            }
+        }
+
+        let music = get_all_music(client, version).await?;
+        let mut versions = self.versions.write().await;
+        let cache = versions.entry(version).or_default();
+        cache.catalogue = Some(Utc::now().timestamp());
+        for m in music.iter() {
+            cache.music.insert(m.id.clone(), Entry::new(m.clone()));
+        }
+        drop(versions);
+        self.touch();
+        Ok(music)
+    }
+
+    pub async fn charts(
+        &self,
+        client: &ArcanaClient,
+        version: u32,
+        music_id: &str,
+    ) -> Result<Vec<Chart>> {
+        if let Some(entry) = self
+            .versions
+            .read()
+            .await
+            .get(&version)
+            .and_then(|v| v.charts.get(music_id))
+            .filter(|e| e.is_fresh(self.ttl))
+        {
+            return Ok(entry.value.clone());
+        }
+
+        let charts = get_charts(client, version, music_id).await?;
+        self.versions
+            .write()
+            .await
+            .entry(version)
+            .or_default()
+            .charts
+            .insert(music_id.to_owned(), Entry::new(charts.clone()));
+        self.touch();
+        Ok(charts)
+    }
+
+    /// Get the profile with the IIDX ID, fetched at most `PROFILE_TTL` ago
+    pub async fn profile(
+        &self,
+        client: &ArcanaClient,
+        version: u32,
+        iidx_id: &str,
+    ) -> Result<Option<Profile>> {
+        if let Some(entry) = self
+            .versions
+            .read()
+            .await
+            .get(&version)
+            .and_then(|v| v.profiles.get(iidx_id))
+            .filter(|e| e.is_fresh(PROFILE_TTL))
+        {
+            return Ok(Some(entry.value.clone()));
+        }
+
+        let profile = get_profile_using_id(client, version, iidx_id).await?.pop();
+        if let Some(p) = &profile {
+            self.versions
+                .write()
+                .await
+                .entry(version)
+                .or_default()
+                .profiles
+                .insert(iidx_id.to_owned(), Entry::new(p.clone()));
+        }
+        Ok(profile)
+    }
+
+    /// Fetch all music and charts of the version into the cache, returning
+    /// how many music and charts are cached
+    pub async fn warm(&self, client: &ArcanaClient, version: u32) -> Result<(usize, usize)> {
+        let music = get_all_music(client, version).await?;
+        let charts = get_all_charts(client, version).await?;
+        let counts = (music.len(), charts.len());
+
+        let mut cache = VersionCache {
+            catalogue: Some(Utc::now().timestamp()),
+            music: music
+                .into_iter()
+                .map(|m| (m.id.clone(), Entry::new(m)))
+                .collect(),
+            ..Default::default()
+        };
+        let mut charts_by_music: HashMap<String, Vec<Chart>> = HashMap::new();
+        for c in charts {
+            charts_by_music
+                .entry(c.music_id.clone())
+                .or_default()
+                .push(c);
+        }
+        // music without any chart still gets an entry to avoid refetching
+        for id in cache.music.keys() {
+            charts_by_music.entry(id.clone()).or_default();
+        }
+        cache.charts = charts_by_music
+            .into_iter()
+            .map(|(id, c)| (id, Entry::new(c)))
+            .collect();
+
+        self.versions.write().await.insert(version, cache);
+        self.save().await;
+        Ok(counts)
+    }
+
+    /// Drop the cached metadata of the version, or of all versions
+    pub async fn invalidate(&self, version: Option<u32>) {
+        match version {
+            Some(version) => {
+                self.versions.write().await.remove(&version);
+            }
+            None => self.versions.write().await.clear(),
+        }
+        self.save().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arcana::mock::MockArcana;
+
+    #[tokio::test]
+    async fn test_cache() {
+        let mut arcana = MockArcana::new().await;
+        let charts = arcana
+            .fixture(
+                "/iidx/28/charts/",
+                &[("where", r#"{"music_id":"G6vGmV2XC2Y"}"#)],
+                "charts",
+            )
+            .await
+            .expect(2);
+        let client = arcana.client();
+        let path = std::env::temp_dir().join(format!("arcana-cache-{}.json", std::process::id()));
+
+        let cache = MetadataCache::load(&path, Duration::from_secs(3600)).await;
+        assert_eq!(
+            cache
+                .charts(&client, 28, "G6vGmV2XC2Y")
+                .await
+                .unwrap()
+                .len(),
+            3
+        );
+        assert_eq!(
+            cache
+                .charts(&client, 28, "G6vGmV2XC2Y")
+                .await
+                .unwrap()
+                .len(),
+            3
+        );
+
+        // survives a restart once flushed
+        cache.flush().await;
+        let cache = MetadataCache::load(&path, Duration::from_secs(3600)).await;
+        assert_eq!(
+            cache
+                .charts(&client, 28, "G6vGmV2XC2Y")
+                .await
+                .unwrap()
+                .len(),
+            3
+        );
+
+        cache.invalidate(Some(28)).await;
+        assert_eq!(
+            cache
+                .charts(&client, 28, "G6vGmV2XC2Y")
+                .await
+                .unwrap()
+                .len(),
+            3
+        );
+        charts.assert_async().await;
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -1,20 +1,25 @@
-use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::fmt;
+use serde_json::json;
+use std::{fmt, str::FromStr};
 
-use super::get_resp;
+use super::{get_items, Music};
+use crate::arcana::{
+    get_music_charts, ArcanaClient, GameChart, PlayStyle, Query, Result, MAX_PAGE_SIZE,
+};
 
-#[derive(Debug, Deserialize, Serialize)]
+/// Chart difficulty, named as by Arcana while the short names used by the
+/// SP12 and BPI data are accepted too
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
 pub enum Difficulty {
-    #[serde(rename = "BEGINNER")]
+    #[serde(rename = "BEGINNER", alias = "B")]
     Beginner,
-    #[serde(rename = "NORMAL")]
+    #[serde(rename = "NORMAL", alias = "N")]
     Normal,
-    #[serde(rename = "HYPER")]
+    #[serde(rename = "HYPER", alias = "H")]
     Hyper,
-    #[serde(rename = "ANOTHER")]
+    #[serde(rename = "ANOTHER", alias = "A")]
     Another,
-    #[serde(rename = "BLACK")]
+    #[serde(rename = "BLACK", alias = "L", alias = "LEGGENDARIA")]
     Leggendaria,
 }
 
@@ -30,24 +35,35 @@ impl fmt::Display for Difficulty {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub enum PlayStyle {
-    #[serde(rename = "SINGLE")]
-    Single,
-    #[serde(rename = "DOUBLE")]
-    Double,
+impl Difficulty {
+    /// Abbreviation of the difficulty, e.g. `A`
+    pub fn short(&self) -> &'static str {
+        match self {
+            Difficulty::Beginner => "B",
+            Difficulty::Normal => "N",
+            Difficulty::Hyper => "H",
+            Difficulty::Another => "A",
+            Difficulty::Leggendaria => "L",
+        }
+    }
 }
 
-impl fmt::Display for PlayStyle {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            PlayStyle::Single => write!(f, "SP"),
-            PlayStyle::Double => write!(f, "DP"),
+impl FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "B" | "BEGINNER" => Ok(Difficulty::Beginner),
+            "N" | "NORMAL" => Ok(Difficulty::Normal),
+            "H" | "HYPER" => Ok(Difficulty::Hyper),
+            "A" | "ANOTHER" => Ok(Difficulty::Another),
+            "L" | "LEGGENDARIA" | "BLACK" => Ok(Difficulty::Leggendaria),
+            _ => Err(format!("invalid difficulty: {}", s)),
         }
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Chart {
     #[serde(rename = "_id")]
     pub id: String,
@@ -75,24 +91,71 @@ impl Default for Chart {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-struct ChartResp {
-    #[serde(rename = "_items")]
-    items: Vec<Chart>,
+impl GameChart for Chart {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+pub async fn get_charts(client: &ArcanaClient, version: u32, music_id: &str) -> Result<Vec<Chart>> {
+    get_music_charts::<Music>(client, version, music_id).await
 }
 
-pub async fn get_charts(version: u32, music_id: &str) -> Result<Vec<Chart>> {
-    let request = get_resp(version, "charts/", &[("music_id", music_id)]).await?;
-    let chart_resp: ChartResp = request.json().await?;
-    Ok(chart_resp.items)
+/// Get all charts of the level in the play style
+pub async fn get_level_charts(
+    client: &ArcanaClient,
+    version: u32,
+    play_style: PlayStyle,
+    level: u32,
+) -> Result<Vec<Chart>> {
+    get_items(
+        client,
+        version,
+        "charts/",
+        &Query::new()
+            .eq("play_style", json!(play_style))
+            .eq("rating", level),
+        None,
+    )
+    .await
+}
+
+/// Get all charts of the version
+pub async fn get_all_charts(client: &ArcanaClient, version: u32) -> Result<Vec<Chart>> {
+    get_items(
+        client,
+        version,
+        "charts/",
+        &Query::new().max_results(MAX_PAGE_SIZE),
+        None,
+    )
+    .await
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::arcana::mock::MockArcana;
 
     #[tokio::test]
-    async fn test_get_profile() {
-        println!("{:?}", get_charts(28, "G6vGmV2XC2Y").await.unwrap())
+    async fn test_get_charts() {
+        let mut arcana = MockArcana::new().await;
+        arcana
+            .fixture(
+                "/iidx/28/charts/",
+                &[("where", r#"{"music_id":"G6vGmV2XC2Y"}"#)],
+                "charts",
+            )
+            .await;
+
+        let charts = get_charts(&arcana.client(), 28, "G6vGmV2XC2Y")
+            .await
+            .unwrap();
+        assert_eq!(charts.len(), 3);
+        assert_eq!(charts[1].id, "8bWq2LkPz0x");
+        assert_eq!(charts[1].notes, 1621);
+        assert_eq!(charts[1].rating, 12);
+        assert!(matches!(charts[2].play_style, PlayStyle::Double));
+        assert!(matches!(charts[2].difficulty, Difficulty::Another));
     }
 }
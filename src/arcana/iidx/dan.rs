@@ -0,0 +1,165 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{fmt, str::FromStr};
+
+/// Dan ranks of SP and DP, ordered from the lowest to the highest
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DanRank {
+    Kyu7,
+    Kyu6,
+    Kyu5,
+    Kyu4,
+    Kyu3,
+    Kyu2,
+    Kyu1,
+    Dan1,
+    Dan2,
+    Dan3,
+    Dan4,
+    Dan5,
+    Dan6,
+    Dan7,
+    Dan8,
+    Dan9,
+    Dan10,
+    Chuuden,
+    Kaiden,
+    /// Rank reported by Arcana that is not known yet, kept as named
+    Other(String),
+}
+
+/// Dan ranks with their Japanese and English names
+const DAN_RANKS: [(DanRank, &str, &str); 19] = [
+    (DanRank::Kyu7, "七級", "7th Kyu"),
+    (DanRank::Kyu6, "六級", "6th Kyu"),
+    (DanRank::Kyu5, "五級", "5th Kyu"),
+    (DanRank::Kyu4, "四級", "4th Kyu"),
+    (DanRank::Kyu3, "三級", "3rd Kyu"),
+    (DanRank::Kyu2, "二級", "2nd Kyu"),
+    (DanRank::Kyu1, "一級", "1st Kyu"),
+    (DanRank::Dan1, "初段", "1st Dan"),
+    (DanRank::Dan2, "二段", "2nd Dan"),
+    (DanRank::Dan3, "三段", "3rd Dan"),
+    (DanRank::Dan4, "四段", "4th Dan"),
+    (DanRank::Dan5, "五段", "5th Dan"),
+    (DanRank::Dan6, "六段", "6th Dan"),
+    (DanRank::Dan7, "七段", "7th Dan"),
+    (DanRank::Dan8, "八段", "8th Dan"),
+    (DanRank::Dan9, "九段", "9th Dan"),
+    (DanRank::Dan10, "十段", "10th Dan"),
+    (DanRank::Chuuden, "中伝", "Chuuden"),
+    (DanRank::Kaiden, "皆伝", "Kaiden"),
+];
+
+impl DanRank {
+    fn names(&self) -> (&str, &str) {
+        if let DanRank::Other(name) = self {
+            return (name, name);
+        }
+        DAN_RANKS
+            .iter()
+            .find(|(rank, _, _)| rank == self)
+            .map(|(_, japanese, english)| (*japanese, *english))
+            .expect("every known dan rank is named")
+    }
+
+    /// Name shown in game and by Arcana, e.g. `七段`
+    pub fn japanese(&self) -> &str {
+        self.names().0
+    }
+
+    /// English name, e.g. `7th Dan`, or the name from Arcana if unknown
+    pub fn english(&self) -> &str {
+        self.names().1
+    }
+
+    /// Name with Arabic numerals, e.g. `7段` or `1段` for `初段`
+    fn numbered(&self) -> Option<String> {
+        let index = DAN_RANKS.iter().position(|(rank, _, _)| rank == self)?;
+        match index {
+            0..=6 => Some(format!("{}級", 7 - index)),
+            7..=16 => Some(format!("{}段", index - 6)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for DanRank {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DanRank::Other(name) => write!(f, "{}", name),
+            _ => write!(f, "{} ({})", self.japanese(), self.english()),
+        }
+    }
+}
+
+impl FromStr for DanRank {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let s = s.trim();
+        DAN_RANKS
+            .iter()
+            .map(|(rank, _, _)| rank.clone())
+            .find(|rank| {
+                rank.japanese() == s
+                    || rank.english().eq_ignore_ascii_case(s)
+                    || rank.numbered().as_deref() == Some(s)
+                    || (*rank == DanRank::Chuuden && s.eq_ignore_ascii_case("Chuden"))
+            })
+            .ok_or_else(|| format!("invalid dan rank: {}", s))
+    }
+}
+
+impl Serialize for DanRank {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.japanese())
+    }
+}
+
+impl<'de> Deserialize<'de> for DanRank {
+    /// Ranks Arcana reports that are not known yet are kept rather than
+    /// failing the whole profile
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(name.parse().unwrap_or_else(|e| {
+            log::warn!("{}", e);
+            DanRank::Other(name)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dan_rank() {
+        assert_eq!("七段".parse(), Ok(DanRank::Dan7));
+        assert_eq!("初段".parse(), Ok(DanRank::Dan1));
+        assert_eq!("1段".parse(), Ok(DanRank::Dan1));
+        assert_eq!("7級".parse(), Ok(DanRank::Kyu7));
+        assert_eq!("10th dan".parse(), Ok(DanRank::Dan10));
+        assert_eq!("皆伝".parse(), Ok(DanRank::Kaiden));
+        assert!("八級".parse::<DanRank>().is_err());
+
+        assert!(DanRank::Kyu1 < DanRank::Dan1);
+        assert!(DanRank::Dan10 < DanRank::Chuuden);
+        assert!(DanRank::Chuuden < DanRank::Kaiden);
+        assert_eq!(DanRank::Dan7.to_string(), "七段 (7th Dan)");
+
+        let rank: Option<DanRank> = serde_json::from_str("\"七段\"").unwrap();
+        assert_eq!(rank, Some(DanRank::Dan7));
+        let rank: Option<DanRank> = serde_json::from_str("null").unwrap();
+        assert_eq!(rank, None);
+        // unknown ranks are kept as named and ordered above the known ones
+        let rank: Option<DanRank> = serde_json::from_str("\"十一段\"").unwrap();
+        assert_eq!(rank, Some(DanRank::Other("十一段".to_owned())));
+        assert!(DanRank::Kaiden < DanRank::Other("十一段".to_owned()));
+        assert_eq!(DanRank::Other("十一段".to_owned()).to_string(), "十一段");
+        assert_eq!(serde_json::to_string(&DanRank::Kaiden).unwrap(), "\"皆伝\"");
+        assert_eq!(
+            serde_json::to_string(&DanRank::Other("十一段".to_owned())).unwrap(),
+            "\"十一段\""
+        );
+    }
+}
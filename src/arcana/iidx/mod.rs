@@ -1,34 +1,30 @@
-use anyhow::Result;
-use lazy_static::lazy_static;
-use reqwest::{Response, Url};
-use serde::Serialize;
+use serde::de::DeserializeOwned;
 
-use super::build_get_request;
+use super::{ArcanaClient, Game, Query, Result};
 
-lazy_static! {
-    static ref IIDX_URL: Url = Url::parse("https://arcana.nu/api/v1/iidx/").unwrap();
-}
-
-async fn get_resp<T: Serialize + ?Sized>(
+/// Collect all items of an IIDX list endpoint matching the query, up to `limit`
+async fn get_items<I: DeserializeOwned>(
+    client: &ArcanaClient,
     version: u32,
     category: &str,
-    args: &T,
-) -> Result<Response> {
-    Ok(
-        build_get_request(IIDX_URL.join(&format!("{}/", version))?.join(category)?)
-            .await?
-            .query(args)
-            .send()
-            .await?,
-    )
+    query: &Query,
+    limit: Option<usize>,
+) -> Result<Vec<I>> {
+    super::get_items(client, Game::Iidx, version, category, query, limit).await
 }
 
+pub mod best;
+pub mod cache;
 pub mod chart;
+pub mod dan;
 pub mod music;
 pub mod profile;
 pub mod score_history;
 
+pub use best::*;
+pub use cache::MetadataCache;
 pub use chart::*;
+pub use dan::*;
 pub use music::*;
 pub use profile::*;
 pub use score_history::*;
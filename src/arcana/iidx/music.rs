@@ -1,9 +1,9 @@
-use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
-use super::get_resp;
+use super::{get_items, Chart};
+use crate::arcana::{ArcanaClient, Game, GameMusic, Query, Result, MAX_PAGE_SIZE};
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Music {
     #[serde(rename = "_id")]
     pub id: String,
@@ -13,35 +13,96 @@ pub struct Music {
     pub title: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-struct MusicResp {
-    #[serde(rename = "_items")]
-    items: Vec<Music>,
+impl GameMusic for Music {
+    const GAME: Game = Game::Iidx;
+    type Chart = Chart;
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    fn artist(&self) -> &str {
+        &self.artist
+    }
 }
 
-pub async fn get_music(version: u32, id: &str) -> Result<Option<Music>> {
-    let request = get_resp(version, "music/", &[("_id", id)]).await?;
-    let mut music_resp: MusicResp = request.json().await?;
-    Ok(music_resp.items.pop())
+pub async fn get_music(client: &ArcanaClient, version: u32, id: &str) -> Result<Option<Music>> {
+    let mut music = get_items(
+        client,
+        version,
+        "music/",
+        &Query::new().eq("_id", id).max_results(1),
+        Some(1),
+    )
+    .await?;
+    Ok(music.pop())
 }
 
-pub async fn get_music_folder(version: u32, folder: u32) -> Result<Vec<Music>> {
-    let request = get_resp(version, "music/", &[("folder", folder)]).await?;
-    let music_resp: MusicResp = request.json().await?;
-    Ok(music_resp.items)
+/// Get all music of the version
+pub async fn get_all_music(client: &ArcanaClient, version: u32) -> Result<Vec<Music>> {
+    get_items(
+        client,
+        version,
+        "music/",
+        &Query::new().max_results(MAX_PAGE_SIZE),
+        None,
+    )
+    .await
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::arcana::mock::MockArcana;
 
     #[tokio::test]
     async fn test_get_music() {
-        println!("{:?}", get_music(28, "G6vGmV2XC2Y").await.unwrap())
+        let mut arcana = MockArcana::new().await;
+        arcana
+            .fixture(
+                "/iidx/28/music/",
+                &[("where", r#"{"_id":"G6vGmV2XC2Y"}"#), ("max_results", "1")],
+                "music_page1",
+            )
+            .await;
+
+        let music = get_music(&arcana.client(), 28, "G6vGmV2XC2Y")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(music.id, "G6vGmV2XC2Y");
+        assert_eq!(music.title, "Mind Mapping");
     }
 
     #[tokio::test]
-    async fn test_get_music_folder() {
-        println!("{:?}", get_music_folder(28, 1).await.unwrap())
+    async fn test_get_all_music() {
+        let mut arcana = MockArcana::new().await;
+        arcana
+            .fixture(
+                "/iidx/28/music/",
+                &[("page", "1"), ("max_results", "50")],
+                "music_page1",
+            )
+            .await;
+        arcana
+            .fixture(
+                "/iidx/28/music/",
+                &[("page", "2"), ("max_results", "50")],
+                "music_page2",
+            )
+            .await;
+
+        let music = get_all_music(&arcana.client(), 28).await.unwrap();
+        assert_eq!(
+            music
+                .iter()
+                .map(|m| m.title.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["Mind Mapping", "Rave It!! Rave It!!", "Starlight Vision"]
+        );
     }
 }
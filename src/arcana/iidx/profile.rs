@@ -1,16 +1,17 @@
-use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
-use super::get_resp;
+use super::{get_items, DanRank};
+use crate::arcana::{ArcanaClient, Game, GameProfile, PlayStyle, Query, Result};
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct Score {
     pub dj_points: u32,
     pub plays: u32,
-    pub rank: Option<String>,
+    #[serde(default)]
+    pub rank: Option<DanRank>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Profile {
     #[serde(rename = "_id")]
     pub id: String,
@@ -20,36 +21,123 @@ pub struct Profile {
     pub dp: Score,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-struct ProfileResp {
-    #[serde(rename = "_items")]
-    items: Vec<Profile>,
+impl Profile {
+    /// Stats of the play style
+    pub fn score(&self, play_style: PlayStyle) -> &Score {
+        match play_style {
+            PlayStyle::Single => &self.sp,
+            PlayStyle::Double => &self.dp,
+        }
+    }
 }
 
-pub async fn get_profile(version: u32, dj_name: &str) -> Result<Vec<Profile>> {
-    let request = get_resp(version, "profiles/", &[("dj_name", dj_name)]).await?;
-    let profile_resp: ProfileResp = request.json().await?;
-    Ok(profile_resp.items)
+impl GameProfile for Profile {
+    const GAME: Game = Game::Iidx;
+    const NAME_FIELD: &'static str = "dj_name";
+    const ID_FIELD: &'static str = "iidx_id";
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.dj_name
+    }
+
+    fn game_id(&self) -> &str {
+        &self.iidx_id
+    }
 }
 
-pub async fn get_profile_using_id(version: u32, iidx_id: &str) -> Result<Vec<Profile>> {
-    let request = get_resp(version, "profiles/", &[("iidx_id", iidx_id)]).await?;
-    let profile_resp: ProfileResp = request.json().await?;
-    Ok(profile_resp.items)
+pub async fn get_profile_by_id(
+    client: &ArcanaClient,
+    version: u32,
+    id: &str,
+) -> Result<Option<Profile>> {
+    let mut profiles = get_items(
+        client,
+        version,
+        "profiles/",
+        &Query::new().eq("_id", id).max_results(1),
+        Some(1),
+    )
+    .await?;
+    Ok(profiles.pop())
+}
+
+pub async fn get_profile_using_id(
+    client: &ArcanaClient,
+    version: u32,
+    iidx_id: &str,
+) -> Result<Vec<Profile>> {
+    get_items(
+        client,
+        version,
+        "profiles/",
+        &Query::new().eq("iidx_id", iidx_id),
+        None,
+    )
+    .await
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::arcana::{find_profiles, mock::MockArcana};
 
     #[tokio::test]
-    async fn test_get_profile() {
-        println!("{:?}", get_profile(28, "1015-0869").await.unwrap())
+    async fn test_find_profiles() {
+        let mut arcana = MockArcana::new().await;
+        arcana
+            .fixture(
+                "/iidx/28/profiles/",
+                &[("where", r#"{"dj_name":"ORIGIN"}"#)],
+                "profiles",
+            )
+            .await;
+
+        let profiles: Vec<Profile> = find_profiles(&arcana.client(), 28, "ORIGIN").await.unwrap();
+        assert_eq!(profiles.len(), 1);
+        assert_eq!(profiles[0].id, "C3PttzgAx6F");
+        assert_eq!(profiles[0].iidx_id, "1015-0869");
+        assert_eq!(profiles[0].sp.dj_points, 1532);
+        assert_eq!(profiles[0].sp.rank, Some(DanRank::Dan7));
+        assert_eq!(profiles[0].dp.rank, None);
+    }
+
+    #[tokio::test]
+    async fn test_get_profile_by_id() {
+        let mut arcana = MockArcana::new().await;
+        arcana
+            .fixture(
+                "/iidx/28/profiles/",
+                &[("where", r#"{"_id":"C3PttzgAx6F"}"#), ("max_results", "1")],
+                "profiles",
+            )
+            .await;
+
+        let profile = get_profile_by_id(&arcana.client(), 28, "C3PttzgAx6F")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(profile.dj_name, "ORIGIN");
     }
 
     // test get profile using id
     #[tokio::test]
     async fn test_get_profile_using_id() {
-        println!("{:?}", get_profile_using_id(28, "ORIGIN").await.unwrap())
+        let mut arcana = MockArcana::new().await;
+        arcana
+            .fixture(
+                "/iidx/28/profiles/",
+                &[("where", r#"{"iidx_id":"1015-0869"}"#)],
+                "profiles",
+            )
+            .await;
+
+        let profiles = get_profile_using_id(&arcana.client(), 28, "1015-0869")
+            .await
+            .unwrap();
+        assert_eq!(profiles[0].dj_name, "ORIGIN");
     }
 }
@@ -1,10 +1,12 @@
-use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::{cmp::Ordering, fmt};
+use std::{cmp::Ordering, fmt, str::FromStr};
 
-use super::get_resp;
+use super::get_items;
+pub use crate::arcana::plays;
+use crate::arcana::{ArcanaClient, Game, Query, Result};
 
-#[derive(Debug, Deserialize, Serialize, Eq, PartialEq)]
+/// Clear lamps, ordered from the worst to the best
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, Eq, PartialEq, PartialOrd, Ord, Hash)]
 pub enum Lamp {
     #[serde(rename = "NO_PLAY")]
     NoPlay,
@@ -24,6 +26,17 @@ pub enum Lamp {
     FullCombo,
 }
 
+pub const LAMPS: [Lamp; 8] = [
+    Lamp::NoPlay,
+    Lamp::Failed,
+    Lamp::AssistClear,
+    Lamp::EasyClear,
+    Lamp::Clear,
+    Lamp::HardClear,
+    Lamp::ExHardClear,
+    Lamp::FullCombo,
+];
+
 impl fmt::Display for Lamp {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -38,7 +51,41 @@ impl fmt::Display for Lamp {
         }
     }
 }
-#[derive(Debug, Deserialize, Serialize, Eq, PartialEq)]
+
+impl Lamp {
+    /// Abbreviation of the lamp, e.g. `EXHC`
+    pub fn short(&self) -> &'static str {
+        match self {
+            Lamp::NoPlay => "NP",
+            Lamp::Failed => "F",
+            Lamp::AssistClear => "AC",
+            Lamp::EasyClear => "EC",
+            Lamp::Clear => "C",
+            Lamp::HardClear => "HC",
+            Lamp::ExHardClear => "EXHC",
+            Lamp::FullCombo => "FC",
+        }
+    }
+}
+
+impl FromStr for Lamp {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_uppercase().replace([' ', '-'], "_").as_str() {
+            "NP" | "NO_PLAY" => Ok(Lamp::NoPlay),
+            "F" | "FAILED" => Ok(Lamp::Failed),
+            "AC" | "ASSIST_CLEAR" => Ok(Lamp::AssistClear),
+            "EC" | "EASY_CLEAR" => Ok(Lamp::EasyClear),
+            "C" | "CLEAR" => Ok(Lamp::Clear),
+            "HC" | "HARD_CLEAR" => Ok(Lamp::HardClear),
+            "EXHC" | "EX_HARD_CLEAR" => Ok(Lamp::ExHardClear),
+            "FC" | "FULL_COMBO" => Ok(Lamp::FullCombo),
+            _ => Err(format!("invalid lamp: {}", s)),
+        }
+    }
+}
+#[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq)]
 pub struct ScoreHistory {
     #[serde(rename = "_id")]
     pub id: String,
@@ -65,29 +112,68 @@ impl PartialOrd for ScoreHistory {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-struct ScoreHistoryResp {
-    #[serde(rename = "_items")]
-    items: Vec<ScoreHistory>,
+/// Get plays matching the query, e.g. plays on a chart with
+/// `plays(profile_id).eq("chart_id", chart_id)`
+pub async fn query_score_history(
+    client: &ArcanaClient,
+    version: u32,
+    query: &Query,
+    limit: Option<usize>,
+) -> Result<Vec<ScoreHistory>> {
+    get_items(client, version, "score_history/", query, limit).await
 }
 
-pub async fn get_score_history(version: u32, profile_id: &str) -> Result<Vec<ScoreHistory>> {
-    let request = get_resp(version, "score_history/", &[("profile_id", profile_id)]).await?;
-    let mut profile_resp: ScoreHistoryResp = request.json().await?;
-    profile_resp.items.sort();
-    Ok(profile_resp.items)
+/// Get all plays of a profile
+pub async fn get_score_history(
+    client: &ArcanaClient,
+    version: u32,
+    profile_id: &str,
+) -> Result<Vec<ScoreHistory>> {
+    query_score_history(client, version, &plays(profile_id), None).await
 }
 
-pub async fn get_most_recent(version: u32, profile_id: &str) -> Result<Option<ScoreHistory>> {
-    Ok(get_score_history(version, profile_id).await?.pop())
+/// Get the latest `n` plays matching the query, from the newest to the oldest
+pub async fn get_recent(
+    client: &ArcanaClient,
+    version: u32,
+    query: Query,
+    n: u32,
+) -> Result<Vec<ScoreHistory>> {
+    crate::arcana::get_recent(client, Game::Iidx, version, query, n).await
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::arcana::mock::MockArcana;
 
     #[tokio::test]
-    async fn test_get_most_recent() {
-        println!("{:?}", get_most_recent(28, "C3PttzgAx6F").await.unwrap())
+    async fn test_get_recent() {
+        let mut arcana = MockArcana::new().await;
+        // only the first page is needed although there are more
+        let recent = arcana
+            .fixture(
+                "/iidx/28/score_history/",
+                &[
+                    ("where", r#"{"profile_id":"C3PttzgAx6F"}"#),
+                    ("sort", "-timestamp"),
+                    ("max_results", "1"),
+                    ("page", "1"),
+                ],
+                "score_history",
+            )
+            .await;
+
+        let r = get_recent(&arcana.client(), 28, plays("C3PttzgAx6F"), 1)
+            .await
+            .unwrap()
+            .pop()
+            .unwrap();
+        assert_eq!(r.chart_id, "8bWq2LkPz0x");
+        assert_eq!(r.ex_score, 2741);
+        assert_eq!(r.lamp, Lamp::HardClear);
+        assert_eq!(r.miss_count, Some(21));
+        assert!(r.raised);
+        recent.assert_async().await;
     }
 }
@@ -0,0 +1,74 @@
+use mockito::{Matcher, Mock, Server, ServerGuard};
+use std::time::Duration;
+
+use super::ArcanaClient;
+
+const TOKEN: &str = "arcana-test-token";
+
+/// Read a recorded Arcana response from `fixtures/arcana`
+pub fn fixture(name: &str) -> String {
+    std::fs::read_to_string(format!(
+        "{}/fixtures/arcana/{}.json",
+        env!("CARGO_MANIFEST_DIR"),
+        name
+    ))
+    .unwrap()
+}
+
+/// A local Arcana server serving recorded responses
+pub struct MockArcana {
+    server: ServerGuard,
+}
+
+impl MockArcana {
+    pub async fn new() -> Self {
+        Self {
+            server: Server::new_async().await,
+        }
+    }
+
+    /// A client of the mock server which retries without waiting
+    pub fn client(&self) -> ArcanaClient {
+        let mut client = ArcanaClient::new(format!("{}/", self.server.url()), TOKEN).unwrap();
+        client.backoff = Duration::ZERO;
+        client
+    }
+
+    /// Serve the fixture for GET requests to the path with the query arguments
+    pub async fn fixture(&mut self, path: &str, args: &[(&str, &str)], name: &str) -> Mock {
+        self.server
+            .mock("GET", path)
+            .match_query(Matcher::AllOf(
+                args.iter()
+                    .map(|(k, v)| Matcher::UrlEncoded(k.to_string(), v.to_string()))
+                    .collect(),
+            ))
+            .match_header("authorization", format!("Bearer {}", TOKEN).as_str())
+            .with_header("content-type", "application/json")
+            .with_body(fixture(name))
+            .create_async()
+            .await
+    }
+
+    /// Respond to any GET request to the path with the status and body
+    pub async fn status(&mut self, path: &str, status: usize, body: &str) -> Mock {
+        self.server
+            .mock("GET", path)
+            .match_query(Matcher::Any)
+            .with_status(status)
+            .with_body(body)
+            .create_async()
+            .await
+    }
+
+    /// Rate limit any GET request to the path, asking to retry after the seconds
+    pub async fn rate_limited(&mut self, path: &str, retry_after: u64) -> Mock {
+        self.server
+            .mock("GET", path)
+            .match_query(Matcher::Any)
+            .with_status(429)
+            .with_header("retry-after", &retry_after.to_string())
+            .create_async()
+            .await
+    }
+}
@@ -1,34 +1,276 @@
-use anyhow::Result;
-use reqwest::{Client, IntoUrl, RequestBuilder};
+use reqwest::{Client, IntoUrl, StatusCode, Url};
+use serde::{de::DeserializeOwned, Serialize};
+use std::time::Duration;
 
-use crate::ARCANA_TOKEN;
+pub use error::{ArcanaError, Result};
+pub use game::*;
+pub use page::Page;
+pub use query::Query;
 
-pub async fn build_get_request(url: impl IntoUrl) -> Result<RequestBuilder> {
-    Ok(Client::builder()
-        .build()?
-        .get(url)
-        .bearer_auth(ARCANA_TOKEN))
+const USER_AGENT: &str = concat!("arcmugbot/", env!("CARGO_PKG_VERSION"));
+const TIMEOUT: Duration = Duration::from_secs(10);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const RETRIES: u32 = 3;
+const BACKOFF: Duration = Duration::from_millis(500);
+
+/// HTTP client of the Arcana API, created once and shared by all requests
+#[derive(Debug, Clone)]
+pub struct ArcanaClient {
+    client: Client,
+    base_url: Url,
+    token: String,
+    retries: u32,
+    backoff: Duration,
+}
+
+impl ArcanaClient {
+    pub fn new(base_url: impl IntoUrl, token: &str) -> Result<Self> {
+        Ok(Self {
+            client: Client::builder()
+                .user_agent(USER_AGENT)
+                .timeout(TIMEOUT)
+                .connect_timeout(CONNECT_TIMEOUT)
+                .build()?,
+            base_url: base_url.into_url()?,
+            token: token.to_owned(),
+            retries: RETRIES,
+            backoff: BACKOFF,
+        })
+    }
+
+    /// Get a single page of a list endpoint
+    pub async fn get_page<I, T>(&self, path: &str, args: &T, page: u32) -> Result<Page<I>>
+    where
+        I: DeserializeOwned,
+        T: Serialize + ?Sized,
+    {
+        self.send(path, args, Some(page)).await
+    }
+
+    /// Collect the items of a list endpoint by following its pages, stopping
+    /// early once `limit` items are collected
+    pub async fn get_all<I, T>(&self, path: &str, args: &T, limit: Option<usize>) -> Result<Vec<I>>
+    where
+        I: DeserializeOwned,
+        T: Serialize + ?Sized,
+    {
+        let mut items = Vec::new();
+        for page in 1.. {
+            let mut resp = self.get_page(path, args, page).await?;
+            items.append(&mut resp.items);
+            if let Some(limit) = limit.filter(|l| items.len() >= *l) {
+                items.truncate(limit);
+                break;
+            }
+            if !resp.has_next() {
+                break;
+            }
+        }
+
+        Ok(items)
+    }
+
+    /// Send a GET request to the path relative to the base URL and decode
+    /// the response, retrying with exponential backoff when the server is
+    /// unavailable or rate limiting
+    async fn send<R, T>(&self, path: &str, args: &T, page: Option<u32>) -> Result<R>
+    where
+        R: DeserializeOwned,
+        T: Serialize + ?Sized,
+    {
+        let url = self
+            .base_url
+            .join(path)
+            .map_err(|e| ArcanaError::InvalidUrl(e.to_string()))?;
+        let mut attempt = 0;
+        loop {
+            let mut request = self
+                .client
+                .get(url.clone())
+                .bearer_auth(&self.token)
+                .query(args);
+            if let Some(page) = page {
+                request = request.query(&[("page", page)]);
+            }
+            let resp = match request.send().await {
+                Ok(resp) => resp,
+                Err(e) if attempt < self.retries && (e.is_connect() || e.is_timeout()) => {
+                    let delay = self.backoff * 2u32.pow(attempt);
+                    log::warn!(
+                        "Arcana request to {} failed: {}, retrying in {:?}",
+                        url,
+                        e,
+                        delay
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            let status = resp.status();
+            if status.is_success() {
+                let body = resp.text().await?;
+                return serde_json::from_str(&body)
+                    .map_err(|error| ArcanaError::Decode { error, body });
+            }
+            if attempt >= self.retries
+                || !(status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS)
+            {
+                return Err(ArcanaError::from_status(status));
+            }
+            // respect Retry-After in seconds if the server tells us, but never
+            // wait longer than the last backoff
+            let delay = resp
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse::<u64>().ok())
+                .map(Duration::from_secs)
+                .unwrap_or(self.backoff * 2u32.pow(attempt))
+                .min(self.backoff * 2u32.pow(self.retries));
+            log::warn!(
+                "Arcana responded {} for {}, retrying in {:?}",
+                status,
+                url,
+                delay
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use mock::MockArcana;
+
+    #[tokio::test]
+    async fn test_get_all() {
+        let mut arcana = MockArcana::new().await;
+        let page1 = arcana
+            .fixture("/iidx/28/music/", &[("page", "1")], "music_page1")
+            .await;
+        let page2 = arcana
+            .fixture("/iidx/28/music/", &[("page", "2")], "music_page2")
+            .await;
+        let client = arcana.client();
+
+        let music: Vec<serde_json::Value> =
+            client.get_all("iidx/28/music/", &(), None).await.unwrap();
+        assert_eq!(music.len(), 3);
+        assert_eq!(music[2]["title"], "Starlight Vision");
+
+        let music: Vec<serde_json::Value> = client
+            .get_all("iidx/28/music/", &(), Some(2))
+            .await
+            .unwrap();
+        assert_eq!(music.len(), 2);
+        page1.expect(2).assert_async().await;
+        page2.expect(1).assert_async().await;
+    }
 
     #[tokio::test]
-    async fn test_get() {
-        println!(
-            "{:?}",
-            build_get_request("https://arcana.nu/api/v1/")
-                .await
-                .unwrap()
-                .send()
-                .await
-                .unwrap()
-                .text()
-                .await
-                .unwrap()
+    async fn test_retry() {
+        let mut arcana = MockArcana::new().await;
+        let unavailable = arcana.status("/iidx/28/charts/", 503, "").await;
+        let charts = arcana
+            .fixture("/iidx/28/charts/", &[("page", "1")], "charts")
+            .await;
+
+        let charts_resp: Page<serde_json::Value> = arcana
+            .client()
+            .get_page("iidx/28/charts/", &(), 1)
+            .await
+            .unwrap();
+        assert_eq!(charts_resp.items.len(), 3);
+        unavailable.assert_async().await;
+        charts.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_retry_after() {
+        let mut arcana = MockArcana::new().await;
+        let rate_limited = arcana.rate_limited("/iidx/28/charts/", 86400).await;
+        let charts = arcana
+            .fixture("/iidx/28/charts/", &[("page", "1")], "charts")
+            .await;
+
+        // the day asked for is capped by the backoff of the client
+        let charts_resp: Page<serde_json::Value> = tokio::time::timeout(
+            Duration::from_secs(5),
+            arcana.client().get_page("iidx/28/charts/", &(), 1),
         )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(charts_resp.items.len(), 3);
+        rate_limited.assert_async().await;
+        charts.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_error_status() {
+        let mut arcana = MockArcana::new().await;
+        arcana.status("/iidx/28/profiles/", 401, "").await;
+        arcana.status("/iidx/99/profiles/", 404, "").await;
+        let rate_limited = arcana
+            .status("/iidx/27/profiles/", 429, "")
+            .await
+            .expect(RETRIES as usize + 1);
+        arcana.status("/iidx/26/profiles/", 400, "").await;
+        let client = arcana.client();
+
+        let get = |path: &'static str| {
+            let client = client.clone();
+            async move { client.get_page::<serde_json::Value, _>(path, &(), 1).await }
+        };
+        assert!(matches!(
+            get("iidx/28/profiles/").await,
+            Err(ArcanaError::Unauthorized)
+        ));
+        assert!(matches!(
+            get("iidx/99/profiles/").await,
+            Err(ArcanaError::NotFound)
+        ));
+        assert!(matches!(
+            get("iidx/27/profiles/").await,
+            Err(ArcanaError::RateLimited)
+        ));
+        assert!(matches!(
+            get("iidx/26/profiles/").await,
+            Err(ArcanaError::Status(StatusCode::BAD_REQUEST))
+        ));
+        rate_limited.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_malformed() {
+        let mut arcana = MockArcana::new().await;
+        arcana
+            .status("/iidx/28/music/", 200, "<html>Bad Gateway</html>")
+            .await;
+
+        match arcana
+            .client()
+            .get_page::<serde_json::Value, _>("iidx/28/music/", &(), 1)
+            .await
+        {
+            Err(ArcanaError::Decode { body, .. }) => assert_eq!(body, "<html>Bad Gateway</html>"),
+            r => panic!("unexpected result: {:?}", r),
+        }
     }
 }
 
+#[cfg(test)]
+pub mod mock;
+
+pub mod ddr;
+pub mod error;
+pub mod game;
 pub mod iidx;
+pub mod page;
+pub mod query;
+pub mod sdvx;
+pub mod version;
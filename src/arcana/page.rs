@@ -0,0 +1,22 @@
+use serde::{de::IgnoredAny, Deserialize};
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Links {
+    pub next: Option<IgnoredAny>,
+}
+
+/// A page of an Eve list endpoint
+#[derive(Debug, Deserialize)]
+pub struct Page<T> {
+    #[serde(rename = "_items")]
+    pub items: Vec<T>,
+    #[serde(rename = "_links", default)]
+    pub links: Links,
+}
+
+impl<T> Page<T> {
+    /// Whether Eve links to a next page
+    pub fn has_next(&self) -> bool {
+        self.links.next.is_some()
+    }
+}
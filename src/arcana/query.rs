@@ -0,0 +1,110 @@
+use serde_json::{Map, Value};
+
+/// Builder of Eve `where`, `sort` and `max_results` query arguments
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    conditions: Map<String, Value>,
+    sort: Vec<String>,
+    max_results: Option<u32>,
+}
+
+impl Query {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only match items whose field equals the value
+    pub fn eq(mut self, field: &str, value: impl Into<Value>) -> Self {
+        self.conditions.insert(field.to_owned(), value.into());
+        self
+    }
+
+    /// Only match items whose field is greater than the value
+    pub fn gt(mut self, field: &str, value: impl Into<Value>) -> Self {
+        let mut condition = Map::new();
+        condition.insert("$gt".to_owned(), value.into());
+        self.conditions
+            .insert(field.to_owned(), Value::Object(condition));
+        self
+    }
+
+    /// Only match items whose field equals any of the values
+    pub fn one_of(mut self, field: &str, values: impl IntoIterator<Item = Value>) -> Self {
+        let mut condition = Map::new();
+        condition.insert("$in".to_owned(), Value::Array(values.into_iter().collect()));
+        self.conditions
+            .insert(field.to_owned(), Value::Object(condition));
+        self
+    }
+
+    /// Sort by the field, descending if prefixed with `-`, e.g. `-timestamp`
+    pub fn sort(mut self, field: &str) -> Self {
+        self.sort.push(field.to_owned());
+        self
+    }
+
+    /// Items per page
+    pub fn max_results(mut self, max_results: u32) -> Self {
+        self.max_results = Some(max_results);
+        self
+    }
+
+    /// Arguments to be appended to the request URL
+    pub fn args(&self) -> Vec<(&'static str, String)> {
+        let mut args = Vec::new();
+        if !self.conditions.is_empty() {
+            args.push(("where", Value::Object(self.conditions.clone()).to_string()));
+        }
+        if !self.sort.is_empty() {
+            args.push(("sort", self.sort.join(",")));
+        }
+        if let Some(max_results) = self.max_results {
+            args.push(("max_results", max_results.to_string()));
+        }
+        args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_args() {
+        let query = Query::new()
+            .eq("profile_id", "C3PttzgAx6F")
+            .eq("chart_id", "3rN9ctmLu3V")
+            .sort("-timestamp")
+            .max_results(5);
+        assert_eq!(
+            query.args(),
+            vec![
+                (
+                    "where",
+                    r#"{"chart_id":"3rN9ctmLu3V","profile_id":"C3PttzgAx6F"}"#.to_owned()
+                ),
+                ("sort", "-timestamp".to_owned()),
+                ("max_results", "5".to_owned()),
+            ]
+        );
+        assert!(Query::new().args().is_empty());
+
+        let query = Query::new().gt("timestamp", "2021-10-03T13:01:52Z");
+        assert_eq!(
+            query.args(),
+            vec![(
+                "where",
+                r#"{"timestamp":{"$gt":"2021-10-03T13:01:52Z"}}"#.to_owned()
+            )]
+        );
+
+        let query = Query::new().one_of("_id", ["G6vGmV2XC2Y".into(), "3rN9ctmLu3V".into()]);
+        assert_eq!(
+            query.args(),
+            vec![(
+                "where",
+                r#"{"_id":{"$in":["G6vGmV2XC2Y","3rN9ctmLu3V"]}}"#.to_owned()
+            )]
+        );
+    }
+}
@@ -0,0 +1,7 @@
+pub mod music;
+pub mod profile;
+pub mod score_history;
+
+pub use music::*;
+pub use profile::*;
+pub use score_history::*;
@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+
+use crate::arcana::{Game, GameChart, GameMusic};
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Music {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub artist: String,
+    pub title: String,
+}
+
+impl GameMusic for Music {
+    const GAME: Game = Game::Sdvx;
+    type Chart = Chart;
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    fn artist(&self) -> &str {
+        &self.artist
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Chart {
+    #[serde(rename = "_id")]
+    pub id: String,
+    /// Difficulty as named by Arcana, e.g. `EXHAUST`
+    pub difficulty: String,
+    pub level: u32,
+    pub music_id: String,
+}
+
+impl GameChart for Chart {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+
+use crate::arcana::{Game, GameProfile};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Profile {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub name: String,
+    pub sdvx_id: String,
+}
+
+impl GameProfile for Profile {
+    const GAME: Game = Game::Sdvx;
+    const NAME_FIELD: &'static str = "name";
+    const ID_FIELD: &'static str = "sdvx_id";
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn game_id(&self) -> &str {
+        &self.sdvx_id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arcana::{find_profiles, mock::MockArcana};
+
+    #[tokio::test]
+    async fn test_find_profiles() {
+        let mut arcana = MockArcana::new().await;
+        arcana
+            .fixture(
+                "/sdvx/6/profiles/",
+                &[("where", r#"{"name":"ORIGIN"}"#)],
+                "sdvx_profiles",
+            )
+            .await;
+
+        let profiles: Vec<Profile> = find_profiles(&arcana.client(), 6, "ORIGIN").await.unwrap();
+        assert_eq!(profiles.len(), 1);
+        assert_eq!(profiles[0].id, "Hq2vNs8TkLw");
+        assert_eq!(profiles[0].sdvx_id, "SV-5384-2211");
+    }
+}
@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+
+use crate::arcana::GameScore;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ScoreHistory {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub chart_id: String,
+    /// Clear medal as named by Arcana, e.g. `ULTIMATE_CHAIN`
+    pub lamp: String,
+    pub music_id: String,
+    pub profile_id: String,
+    pub score: u32,
+    pub timestamp: String,
+}
+
+impl GameScore for ScoreHistory {
+    fn music_id(&self) -> &str {
+        &self.music_id
+    }
+
+    fn chart_id(&self) -> &str {
+        &self.chart_id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arcana::{get_recent, mock::MockArcana, plays, Game};
+
+    #[tokio::test]
+    async fn test_get_recent() {
+        let mut arcana = MockArcana::new().await;
+        arcana
+            .fixture(
+                "/sdvx/6/score_history/",
+                &[
+                    ("where", r#"{"profile_id":"Hq2vNs8TkLw"}"#),
+                    ("sort", "-timestamp"),
+                    ("max_results", "10"),
+                ],
+                "sdvx_score_history",
+            )
+            .await;
+
+        let recent: Vec<ScoreHistory> =
+            get_recent(&arcana.client(), Game::Sdvx, 6, plays("Hq2vNs8TkLw"), 10)
+                .await
+                .unwrap();
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].score, 9_912_345);
+        assert_eq!(recent[0].lamp, "ULTIMATE_CHAIN");
+    }
+}
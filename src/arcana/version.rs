@@ -0,0 +1,241 @@
+use std::fmt;
+
+use super::Game;
+
+/// A game version known by its number, name and aliases
+#[derive(Debug)]
+pub struct Version {
+    pub number: u32,
+    pub name: &'static str,
+    /// Abbreviations and Japanese names
+    pub aliases: &'static [&'static str],
+}
+
+const IIDX_VERSIONS: &[Version] = &[
+    Version {
+        number: 20,
+        name: "tricoro",
+        aliases: &["トリコロ"],
+    },
+    Version {
+        number: 21,
+        name: "SPADA",
+        aliases: &["スパーダ"],
+    },
+    Version {
+        number: 22,
+        name: "PENDUAL",
+        aliases: &["ペンデュアル"],
+    },
+    Version {
+        number: 23,
+        name: "copula",
+        aliases: &["コピュラ"],
+    },
+    Version {
+        number: 24,
+        name: "SINOBUZ",
+        aliases: &["シノバズ"],
+    },
+    Version {
+        number: 25,
+        name: "CANNON BALLERS",
+        aliases: &["CB", "キャノンボーラーズ"],
+    },
+    Version {
+        number: 26,
+        name: "Rootage",
+        aliases: &["ルーテージ"],
+    },
+    Version {
+        number: 27,
+        name: "HEROIC VERSE",
+        aliases: &["HV", "ヒロイックヴァース"],
+    },
+    Version {
+        number: 28,
+        name: "BISTROVER",
+        aliases: &["BISTRO", "ビストロオーバー"],
+    },
+    Version {
+        number: 29,
+        name: "CastHour",
+        aliases: &["CH", "キャストアワー"],
+    },
+    Version {
+        number: 30,
+        name: "RESIDENT",
+        aliases: &["レジデント"],
+    },
+    Version {
+        number: 31,
+        name: "EPOLIS",
+        aliases: &["エポリス"],
+    },
+    Version {
+        number: 32,
+        name: "Pinky Crush",
+        aliases: &["PC", "ピンキークラッシュ"],
+    },
+];
+
+const SDVX_VERSIONS: &[Version] = &[
+    Version {
+        number: 1,
+        name: "BOOTH",
+        aliases: &["ブース"],
+    },
+    Version {
+        number: 2,
+        name: "II -infinite infection-",
+        aliases: &["II", "インフィニットインフェクション"],
+    },
+    Version {
+        number: 3,
+        name: "III GRAVITY WARS",
+        aliases: &["GW", "III", "グラビティウォーズ"],
+    },
+    Version {
+        number: 4,
+        name: "IV HEAVENLY HAVEN",
+        aliases: &["HH", "IV", "ヘヴンリーヘヴン"],
+    },
+    Version {
+        number: 5,
+        name: "VIVID WAVE",
+        aliases: &["VW", "ヴィヴィッドウェーブ"],
+    },
+    Version {
+        number: 6,
+        name: "EXCEED GEAR",
+        aliases: &["EG", "エクシードギア"],
+    },
+    Version {
+        number: 7,
+        name: "∇",
+        aliases: &["NABLA", "ナブラ"],
+    },
+];
+
+const DDR_VERSIONS: &[Version] = &[
+    Version {
+        number: 14,
+        name: "DDR 2013",
+        aliases: &[],
+    },
+    Version {
+        number: 15,
+        name: "DDR 2014",
+        aliases: &[],
+    },
+    Version {
+        number: 16,
+        name: "DDR A",
+        aliases: &["A"],
+    },
+    Version {
+        number: 17,
+        name: "DDR A20",
+        aliases: &["A20"],
+    },
+    Version {
+        number: 18,
+        name: "DDR A20 PLUS",
+        aliases: &["A20PLUS", "A20+"],
+    },
+    Version {
+        number: 19,
+        name: "DDR A3",
+        aliases: &["A3"],
+    },
+    Version {
+        number: 20,
+        name: "DDR WORLD",
+        aliases: &["WORLD"],
+    },
+];
+
+/// Keep only letters and digits in upper case, so that e.g. `heroic verse`
+/// and `HEROIC_VERSE` match `HEROIC VERSE`
+fn normalize(s: &str) -> String {
+    s.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_uppercase)
+        .collect()
+}
+
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Game::Iidx => write!(f, "IIDX"),
+            Game::Sdvx => write!(f, "SOUND VOLTEX"),
+            Game::Ddr => write!(f, "DDR"),
+        }
+    }
+}
+
+impl Game {
+    /// Known versions of the game, from the oldest to the newest
+    pub fn versions(&self) -> &'static [Version] {
+        match self {
+            Game::Iidx => IIDX_VERSIONS,
+            Game::Sdvx => SDVX_VERSIONS,
+            Game::Ddr => DDR_VERSIONS,
+        }
+    }
+
+    /// Parse a version number, name or alias, which are matched ignoring
+    /// case, spaces and symbols; numbers of unknown versions are kept as is
+    pub fn parse_version(&self, s: &str) -> Option<u32> {
+        let s = s.trim();
+        if let Ok(number) = s.parse() {
+            return Some(number);
+        }
+        let names = |v: &&Version| std::iter::once(v.name).chain(v.aliases.iter().copied());
+        // exact names first, so that e.g. `A20+` is not taken as `A20`
+        let version = self
+            .versions()
+            .iter()
+            .find(|v| names(v).any(|n| n.eq_ignore_ascii_case(s)));
+        let key = normalize(s);
+        version
+            .or_else(|| {
+                self.versions()
+                    .iter()
+                    .find(|v| !key.is_empty() && names(v).any(|n| normalize(n) == key))
+            })
+            .map(|v| v.number)
+    }
+
+    /// Version number along with its name if known, e.g. `28 BISTROVER`
+    pub fn version_name(&self, number: u32) -> String {
+        match self.versions().iter().find(|v| v.number == number) {
+            Some(v) => format!("{} {}", number, v.name),
+            None => number.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(Game::Iidx.parse_version("28"), Some(28));
+        assert_eq!(Game::Iidx.parse_version("bistrover"), Some(28));
+        assert_eq!(Game::Iidx.parse_version("HEROIC_VERSE"), Some(27));
+        assert_eq!(Game::Iidx.parse_version("キャストアワー"), Some(29));
+        assert_eq!(Game::Iidx.parse_version("ORIGIN"), None);
+        assert_eq!(Game::Sdvx.parse_version("∇"), Some(7));
+        assert_eq!(Game::Sdvx.parse_version("eg"), Some(6));
+        assert_eq!(Game::Ddr.parse_version("A20+"), Some(18));
+        assert_eq!(Game::Ddr.parse_version("A20"), Some(17));
+        assert_eq!(Game::Ddr.parse_version("BISTROVER"), None);
+        assert_eq!(Game::Ddr.parse_version("ddr 2014"), Some(15));
+        assert_eq!(Game::Ddr.parse_version(""), None);
+
+        assert_eq!(Game::Iidx.version_name(28), "28 BISTROVER");
+        assert_eq!(Game::Iidx.version_name(99), "99");
+    }
+}
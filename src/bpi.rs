@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::arcana::iidx::Difficulty;
+
+/// Default exponent of the BPI curve
+const DEFAULT_COEF: f64 = 1.175;
+/// Lowest BPI reported for scores far below the kaiden average
+pub const MIN_BPI: f64 = -15.0;
+
+/// Reference data of a chart
+#[derive(PartialEq, Debug, Clone, Deserialize, Serialize)]
+pub struct BpiChart {
+    pub title: String,
+    pub difficulty: Difficulty,
+    pub notes: u32,
+    /// World record EX score
+    pub wr: u32,
+    /// Kaiden average EX score
+    pub avg: u32,
+    /// Chart specific exponent, if any
+    pub coef: Option<f64>,
+}
+
+pub type BpiTable = Arc<RwLock<Vec<BpiChart>>>;
+
+fn pgf(score: u32, max: u32) -> f64 {
+    if score >= max {
+        max as f64 * 0.8
+    } else {
+        let rate = score as f64 / max as f64;
+        1.0 + (rate - 0.5) / (1.0 - rate)
+    }
+}
+
+/// Calculate the Beat Power Index of an EX score on the chart
+pub fn calc_bpi(chart: &BpiChart, ex_score: u32) -> Option<f64> {
+    let max = chart.notes * 2;
+    if ex_score > max || chart.wr <= chart.avg {
+        return None;
+    }
+    let coef = chart.coef.filter(|c| *c > 0.0).unwrap_or(DEFAULT_COEF);
+    let avg = pgf(chart.avg, max);
+    let s = (pgf(ex_score, max) / avg).ln();
+    let z = (pgf(chart.wr, max) / avg).ln();
+
+    Some(if s >= 0.0 {
+        100.0 * s.powf(coef) / z.powf(coef)
+    } else {
+        (-100.0 * (-s).powf(coef) / z.powf(coef)).max(MIN_BPI)
+    })
+}
+
+/// `x` to the power of `e` keeping the sign of `x`
+fn signed_powf(x: f64, e: f64) -> f64 {
+    x.signum() * x.abs().powf(e)
+}
+
+/// Total BPI over the BPI of every chart, counting unplayed charts as
+/// `MIN_BPI`, as BPIManager does
+pub fn total_bpi(bpis: &[f64]) -> Option<f64> {
+    if bpis.is_empty() {
+        return None;
+    }
+    let n = bpis.len() as f64;
+    // the exponent grows with the number of charts
+    let k = n.log2().max(1.0);
+    let mean = bpis.iter().map(|bpi| signed_powf(*bpi, k)).sum::<f64>() / n;
+    Some(signed_powf(mean, 1.0 / k))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calc_bpi() {
+        let chart = BpiChart {
+            title: "Test".to_owned(),
+            difficulty: Difficulty::Another,
+            notes: 1500,
+            wr: 2900,
+            avg: 2500,
+            coef: None,
+        };
+        assert!(calc_bpi(&chart, 2500).unwrap().abs() < 1e-9);
+        assert!((calc_bpi(&chart, 2900).unwrap() - 100.0).abs() < 1e-9);
+        assert_eq!(calc_bpi(&chart, 0).unwrap(), MIN_BPI);
+        assert!(calc_bpi(&chart, 3001).is_none());
+    }
+
+    #[test]
+    fn test_total_bpi() {
+        assert!(total_bpi(&[]).is_none());
+        assert!((total_bpi(&[50.0; 4]).unwrap() - 50.0).abs() < 1e-9);
+        assert!((total_bpi(&[-10.0; 8]).unwrap() + 10.0).abs() < 1e-9);
+        // a strong chart weighs more than a weak one
+        assert!(total_bpi(&[80.0, 0.0, 0.0, 0.0]).unwrap() > 20.0);
+        assert!(total_bpi(&[80.0, MIN_BPI, MIN_BPI, MIN_BPI]).unwrap() > 0.0);
+    }
+}
@@ -0,0 +1,138 @@
+use lisp_rs::lisp_rs_eval;
+use teloxide::utils::command::ParseError;
+
+use crate::{
+    handlers::{
+        chuni_tolerance_calc::ChuniTolerance,
+        ddr_score_calc::DdrScore,
+        iidx_gauge_calc::GaugeCalc,
+        sdvx::{GradeCalc, ToleranceCalc, VolforceCalc},
+    },
+    maimai_courses::Submission,
+};
+
+/// A calculator which parses its arguments, computes the result and renders
+/// it as a reply
+pub trait ScoreCalculator: Sized {
+    /// Name of the calculator in the Lisp REPL and inline mode
+    const NAME: &'static str;
+    /// Usage shown when the arguments are invalid
+    const USAGE: &'static str;
+
+    type Output;
+
+    fn parse(input: &str) -> Result<Self, ParseError>;
+    fn compute(&self) -> Self::Output;
+    fn render(&self, output: &Self::Output) -> String;
+
+    /// Parse, compute and render in one go
+    fn evaluate(input: &str) -> String {
+        match Self::parse(input) {
+            Ok(calculator) => calculator.render(&calculator.compute()),
+            Err(e) => format!("{}\nUsage: {}", e, Self::USAGE),
+        }
+    }
+}
+
+type Evaluate = fn(&str) -> String;
+
+/// All calculators reachable by name
+const CALCULATORS: &[(&str, &str, Evaluate)] = &[
+    (Submission::NAME, Submission::USAGE, Submission::evaluate),
+    (
+        ChuniTolerance::NAME,
+        ChuniTolerance::USAGE,
+        ChuniTolerance::evaluate,
+    ),
+    (DdrScore::NAME, DdrScore::USAGE, DdrScore::evaluate),
+    (GaugeCalc::NAME, GaugeCalc::USAGE, GaugeCalc::evaluate),
+    (
+        VolforceCalc::NAME,
+        VolforceCalc::USAGE,
+        VolforceCalc::evaluate,
+    ),
+    (GradeCalc::NAME, GradeCalc::USAGE, GradeCalc::evaluate),
+    (
+        ToleranceCalc::NAME,
+        ToleranceCalc::USAGE,
+        ToleranceCalc::evaluate,
+    ),
+];
+
+/// Usages of all calculators
+pub fn usages() -> impl Iterator<Item = &'static str> {
+    CALCULATORS.iter().map(|(_, usage, _)| *usage)
+}
+
+/// Evaluate the calculator with the given name
+pub fn evaluate(name: &str, input: &str) -> Option<String> {
+    CALCULATORS
+        .iter()
+        .find(|(n, _, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, _, evaluate)| evaluate(input))
+}
+
+/// Split a Lisp list into its top level elements
+fn split_forms(input: &str) -> Vec<&str> {
+    let mut forms = Vec::new();
+    let mut depth = 0;
+    let mut start = None;
+    for (i, c) in input.char_indices() {
+        match c {
+            '(' => {
+                if depth == 0 {
+                    start.get_or_insert(i);
+                }
+                depth += 1;
+            }
+            ')' => depth -= 1,
+            c if c.is_whitespace() && depth == 0 => {
+                if let Some(s) = start.take() {
+                    forms.push(&input[s..i]);
+                }
+            }
+            _ => {
+                start.get_or_insert(i);
+            }
+        }
+    }
+    if let Some(s) = start {
+        forms.push(&input[s..]);
+    }
+    forms
+}
+
+/// Evaluate a Lisp call of a calculator, e.g. `(vf 18 (+ 9800000 50000) puc)`
+///
+/// Nested forms are evaluated by the Lisp interpreter before being passed
+/// to the calculator.
+pub fn lisp_call(input: &str) -> Option<String> {
+    let body = input.trim().strip_prefix('(')?.strip_suffix(')')?;
+    let mut forms = split_forms(body).into_iter();
+    let name = forms.next()?;
+    let args = forms
+        .map(|form| {
+            if form.starts_with('(') {
+                lisp_rs_eval(form)
+            } else {
+                form.trim_matches('"').to_owned()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ");
+    evaluate(name, &args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lisp_call() {
+        assert_eq!(
+            lisp_call("(vf 20 (* 1000 10000) \"puc\")"),
+            evaluate("vf", "20 10000000 puc")
+        );
+        assert!(lisp_call("(+ 1 2)").is_none());
+    }
+}
@@ -1,7 +1,13 @@
 use std::{collections::VecDeque, str::SplitWhitespace};
 use teloxide::utils::command::{BotCommands, ParseError};
 
-use crate::maimai_courses::Submission;
+use crate::{
+    arcana::{iidx::Difficulty, Game, PlayStyle},
+    handlers::arcana::{
+        iidx::{board::BoardOptions, recent::RecentOptions},
+        Player,
+    },
+};
 
 pub type Results = VecDeque<[u32; 3]>;
 
@@ -18,16 +24,12 @@ pub enum Command {
     Help,
     #[command(description = "display about")]
     About,
+    #[command(description = "calculate the life remains (/calc LIFE HEAL [[GREAT,GOOD,MISS]..])")]
+    Calc { input: String },
     #[command(
-        description = "calculate the life remains (/calc LIFE HEAL [[GREAT,GOOD,MISS]..])",
-        parse_with = calc_parser
+        description = "calculate the life remains using custom rule (/calc LIFE HEAL (RULE: [GREAT,GOOD,MISS]) [[GREAT,GOOD,MISS]..])"
     )]
-    Calc { submission: Submission },
-    #[command(
-        description = "calculate the life remains using custom rule (/calc LIFE HEAL (RULE: [GREAT,GOOD,MISS]) [[GREAT,GOOD,MISS]..])",
-        parse_with = calc_parser
-    )]
-    CalcCustom { submission: Submission },
+    CalcCustom { input: String },
     #[command(
         description = "submit maimai course of current month (/submit LEVEL [[GREAT,GOOD,MISS]..])",
         parse_with = submit_parser
@@ -42,38 +44,158 @@ pub enum Command {
     #[command(description = "get rank for the course level (/rank LEVEL)")]
     Rank { level: u32 },
     #[command(
-        description = "get user's profile on Arcana with given game version and DJ name/IIDX ID (/iidxprofile VERSION DJ_NAME/IIDX_ID)",
-        parse_with = "split"
+        description = "get user's profile on Arcana with given game version and DJ name/IIDX ID, or your linked profile (/iidxprofile [VERSION DJ_NAME/IIDX_ID])",
+        parse_with = profile_parser
+    )]
+    IIDXProfile { player: Option<Player> },
+    #[command(
+        description = "show how DJ POINTS, plays and dan ranks of a linked profile changed (/iidxprogress [VERSION DJ_NAME/IIDX_ID])",
+        parse_with = profile_parser
+    )]
+    IIDXProgress { player: Option<Player> },
+    #[command(
+        description = "search IIDX music by title, artist or genre (/iidxmusic [VERSION] TITLE)",
+        parse_with = music_parser
+    )]
+    IIDXMusic { version: Option<u32>, title: String },
+    #[command(
+        description = "list recent scores, optionally filtered by SP/DP, difficulty, LV and lamp (/iidxrecent [VERSION DJ_NAME/IIDX_ID] [N] [SP/DP] [B/N/H/A/L] [LV12] [F/AC/EC/C/HC/EXHC/FC])",
+        parse_with = recent_parser
+    )]
+    IIDXRecent {
+        player: Option<Player>,
+        options: RecentOptions,
+    },
+    #[command(
+        description = "get personal bests on each chart of a song (/iidxbest [VERSION DJ_NAME/IIDX_ID] TITLE)",
+        parse_with = best_parser
+    )]
+    IIDXBest {
+        player: Option<Player>,
+        title: String,
+    },
+    #[command(
+        description = "count clear lamps of a level and list uncleared charts (/iidxlamps [VERSION DJ_NAME/IIDX_ID] LEVEL [SP/DP])",
+        parse_with = lamps_parser
+    )]
+    IIDXLamps {
+        player: Option<Player>,
+        level: u32,
+        play_style: PlayStyle,
+    },
+    #[command(
+        description = "compare personal bests of two players, optionally on a level (/iidxvs VERSION PLAYER_A PLAYER_B [LEVEL])",
+        parse_with = vs_parser
+    )]
+    IIDXVs {
+        version: u32,
+        player_a: String,
+        player_b: String,
+        level: Option<u32>,
+    },
+    #[command(
+        description = "rank players in this chat who linked their profiles (/iidxboard [VERSION] [SP/DP] [dj_points/dan/plays])",
+        parse_with = board_parser
+    )]
+    IIDXBoard { options: BoardOptions },
+    #[command(
+        description = "link your Telegram account to an Arcana profile, used when VERSION DJ_NAME/IIDX_ID is omitted (/iidxlink [DJ_NAME/IIDX_ID [VERSION]])",
+        parse_with = link_parser
+    )]
+    IIDXLink { param: String, version: Option<u32> },
+    #[command(
+        description = "post new personal bests of your linked profile in this chat (/iidxnotify on/off)"
+    )]
+    IIDXNotify { action: String },
+    #[command(
+        description = "warm or invalidate the IIDX metadata cache (admin only) (/iidxcache warm VERSION or /iidxcache invalidate [VERSION])"
+    )]
+    IIDXCache { action: String },
+    #[command(
+        description = "list game versions, which can be given by number, name or alias in other commands"
+    )]
+    Versions,
+    #[command(
+        description = "get SOUND VOLTEX profile on Arcana (/sdvxprofile VERSION NAME/SDVX_ID)",
+        parse_with = sdvx_profile_parser
+    )]
+    SdvxProfile { player: Player },
+    #[command(
+        description = "search SOUND VOLTEX music by title or artist (/sdvxmusic VERSION TITLE)",
+        parse_with = sdvx_music_parser
+    )]
+    SdvxMusic { version: u32, title: String },
+    #[command(
+        description = "list recent SOUND VOLTEX scores (/sdvxrecent VERSION NAME/SDVX_ID [N])",
+        parse_with = sdvx_recent_parser
+    )]
+    SdvxRecent { player: Player, n: u32 },
+    #[command(
+        description = "get DDR profile on Arcana (/ddrprofile VERSION DANCER_NAME/DDR_ID)",
+        parse_with = ddr_profile_parser
+    )]
+    DdrProfile { player: Player },
+    #[command(
+        description = "search DDR music by title or artist (/ddrmusic VERSION TITLE)",
+        parse_with = ddr_music_parser
+    )]
+    DdrMusic { version: u32, title: String },
+    #[command(
+        description = "list recent DDR scores (/ddrrecent VERSION DANCER_NAME/DDR_ID [N])",
+        parse_with = ddr_recent_parser
+    )]
+    DdrRecent { player: Player, n: u32 },
+    #[command(
+        description = "calculate CHUNITHM score tolerance (/chunitolerance NOTES [SS/SS+/SSS/SSS+])"
+    )]
+    ChuniTolerance { input: String },
+    #[command(
+        description = "simulate IIDX gauges (/iidxgauge NOTES PG,GR,GD,BD,POOR or /iidxgauge NOTES SEQUENCE of P/G/D/B/X)"
     )]
-    IIDXProfile { version: u32, param: String },
+    IIDXGauge { input: String },
     #[command(
-        description = "search IIDX music with given version and title (/iidxmusic VERSION TITLE)",
-        parse_with = split_into_two
+        description = "calculate SOUND VOLTEX single chart Volforce (/vf LEVEL SCORE PLAYED/COMP/EXC/MXV/UC/PUC)"
     )]
-    IIDXMusic { version: u32, title: String },
+    Vf { input: String },
+    #[command(description = "get SOUND VOLTEX grade of a score (/sdvxgrade SCORE)")]
+    SdvxGrade { input: String },
     #[command(
-        description = "get recent score (/iidxrecent VERSION DJ_NAME/IIDX_ID)",
-        parse_with = "split"
+        description = "calculate SOUND VOLTEX score tolerance (/sdvxtolerance CHAINS TARGET)"
     )]
-    IIDXRecent { version: u32, param: String },
+    SdvxTolerance { input: String },
     #[command(
-        description = "calculate CHUNITHM score tolerance",
-        parse_with = "split"
+        description = "calculate DDR money score and EX score (/ddrscore STEPS FREEZES MARVELOUS,PERFECT,GREAT,GOOD,MISS,OK,NG) or tolerance (/ddrscore STEPS FREEZES TARGET)"
     )]
-    ChuniTolerance { notes: u32, target: String },
+    DdrScore { input: String },
     #[command(description = "Lisp REPL (powered by lisp-rs)")]
     Lisp { input: String },
     #[command(description = "Search IIDX SP12 difficulty table (/sp12 TITLE)")]
     SP12 { title: String },
+    #[command(
+        description = "calculate IIDX BPI (/bpi TITLE DIFFICULTY EX_SCORE)",
+        parse_with = bpi_parser
+    )]
+    Bpi {
+        title: String,
+        difficulty: Difficulty,
+        ex_score: u32,
+    },
+    #[command(
+        description = "calculate the total BPI of SP12 personal bests of a profile, or your linked profile (/bpitotal [VERSION DJ_NAME/IIDX_ID])",
+        parse_with = profile_parser
+    )]
+    BpiTotal { player: Option<Player> },
+    #[command(description = "import BPI reference data (admin only) (/bpiimport URL)")]
+    BpiImport { url: String },
 }
 
-fn next_str_into_u32(from: Option<&str>) -> Result<u32, ParseError> {
+pub fn next_str_into_u32(from: Option<&str>) -> Result<u32, ParseError> {
     from.ok_or_else(|| ParseError::Custom("invalid input".into()))?
         .parse::<u32>()
         .map_err(|e| ParseError::IncorrectFormat(e.into()))
 }
 
-fn parse_score(parts: SplitWhitespace) -> Result<Results, ParseError> {
+pub fn parse_score(parts: SplitWhitespace) -> Result<Results, ParseError> {
     let mut results = VecDeque::new();
     for i in parts {
         let mut result = i.splitn(3, ',');
@@ -86,48 +208,339 @@ fn parse_score(parts: SplitWhitespace) -> Result<Results, ParseError> {
     Ok(results)
 }
 
-/// Parse a score calc command
-fn calc_parser(input: String) -> Result<(Submission,), ParseError> {
+/// Parse a submit command
+fn submit_parser(input: String) -> Result<(u32, Results), ParseError> {
     // The command should satisfy this pattern:
-    // /command LIFE HEAL [[GREAT,GOOD,MISS]..]
+    // /submit LEVEL [[GREAT,GOOD,MISS]..]
     //
     // For example:
-    // /calc 500 30 10,3,1 13,2,0 3,0,0 0,0,0
+    // /submit 10 10,3,1 13,2,0 3,0,0 0,0,0
     let mut parts = input.split_whitespace();
-    let marker = next_str_into_u32(parts.next())?;
-    let heal = next_str_into_u32(parts.next())?;
-    let mut results = parse_score(parts)?;
-    let mut rule = [2, 3, 5];
-    if results.len() == 4 {
-        rule = results.pop_front().unwrap();
+    let level = next_str_into_u32(parts.next())?;
+    let results = parse_score(parts)?;
+
+    Ok((level, results))
+}
+
+/// Parse a version number, name or alias of the game
+fn parse_version(game: Game, from: Option<&str>) -> Result<u32, ParseError> {
+    let from = from.ok_or_else(|| ParseError::Custom("invalid input".into()))?;
+    game.parse_version(from)
+        .ok_or_else(|| ParseError::Custom(format!("unknown {} version: {}", game, from).into()))
+}
+
+/// Split off a leading version of the game if followed by more words
+fn split_version(input: &str, game: Game) -> (Option<u32>, &str) {
+    let input = input.trim();
+    match input.split_once(' ') {
+        Some((version, rest)) => match game.parse_version(version) {
+            Some(version) => (Some(version), rest.trim()),
+            None => (None, input),
+        },
+        None => (None, input),
     }
+}
 
-    Ok((Submission {
-        life: marker,
-        heal,
-        rule,
-        results,
-    },))
+/// Split off a leading `VERSION DJ_NAME/IIDX_ID` if the second word is taken
+/// as a player, otherwise the linked profile is used
+fn split_player(
+    input: &str,
+    game: Game,
+    is_player: impl Fn(&str) -> bool,
+) -> (Option<Player>, &str) {
+    let input = input.trim();
+    let mut parts = input.splitn(3, ' ');
+    if let (Some(Some(version)), Some(param)) =
+        (parts.next().map(|v| game.parse_version(v)), parts.next())
+    {
+        if !param.is_empty() && is_player(param) {
+            return (
+                Some(Player {
+                    version,
+                    param: param.to_owned(),
+                }),
+                parts.next().unwrap_or("").trim(),
+            );
+        }
+    }
+    (None, input)
 }
 
-/// Parse a submit command
-fn submit_parser(input: String) -> Result<(u32, Results), ParseError> {
+/// Parse an IIDX profile command
+fn profile_parser(input: String) -> Result<(Option<Player>,), ParseError> {
     // The command should satisfy this pattern:
-    // /submit LEVEL [[GREAT,GOOD,MISS]..]
+    // /iidxprofile [VERSION DJ_NAME/IIDX_ID]
+    let (player, rest) = split_player(&input, Game::Iidx, |_| true);
+    if !rest.is_empty() {
+        return Err(ParseError::Custom("invalid input".into()));
+    }
+    Ok((player,))
+}
+
+/// Parse an IIDX music command
+fn music_parser(input: String) -> Result<(Option<u32>, String), ParseError> {
+    // The command should satisfy this pattern:
+    // /iidxmusic [VERSION] TITLE
     //
     // For example:
-    // /submit 10 10,3,1 13,2,0 3,0,0 0,0,0
-    let mut parts = input.split_whitespace();
+    // /iidxmusic 28 Mind Mapping
+    let (version, title) = split_version(&input, Game::Iidx);
+    if title.is_empty() {
+        return Err(ParseError::Custom("invalid input".into()));
+    }
+    Ok((version, title.to_owned()))
+}
+
+/// Parse an IIDX best command
+fn best_parser(input: String) -> Result<(Option<Player>, String), ParseError> {
+    // The command should satisfy this pattern:
+    // /iidxbest [VERSION DJ_NAME/IIDX_ID] TITLE
+    //
+    // For example:
+    // /iidxbest 28 ORIGIN Mind Mapping
+    let (player, title) = split_player(&input, Game::Iidx, |_| true);
+    if title.is_empty() {
+        return Err(ParseError::Custom("invalid input".into()));
+    }
+    Ok((player, title.to_owned()))
+}
+
+/// Parse an IIDX lamps command
+fn lamps_parser(input: String) -> Result<(Option<Player>, u32, PlayStyle), ParseError> {
+    // The command should satisfy this pattern:
+    // /iidxlamps [VERSION DJ_NAME/IIDX_ID] LEVEL [SP/DP]
+    //
+    // For example:
+    // /iidxlamps 28 ORIGIN 12 SP
+    let (player, rest) = split_player(&input, Game::Iidx, |p| {
+        p.parse::<u32>().is_err() && p.parse::<PlayStyle>().is_err()
+    });
+    let mut parts = rest.split_whitespace();
     let level = next_str_into_u32(parts.next())?;
-    let results = parse_score(parts)?;
+    let play_style = match parts.next() {
+        Some(p) => p
+            .parse()
+            .map_err(|e: String| ParseError::Custom(e.into()))?,
+        None => PlayStyle::Single,
+    };
+    Ok((player, level, play_style))
+}
 
-    Ok((level, results))
+/// Parse an IIDX recent command
+fn recent_parser(input: String) -> Result<(Option<Player>, RecentOptions), ParseError> {
+    // The command should satisfy this pattern:
+    // /iidxrecent [VERSION DJ_NAME/IIDX_ID] [N] [FILTERS..]
+    //
+    // For example:
+    // /iidxrecent 28 ORIGIN 20 SP LV12 HC
+    let (player, rest) = split_player(&input, Game::Iidx, |p| p.parse::<RecentOptions>().is_err());
+    let options = rest
+        .parse()
+        .map_err(|e: String| ParseError::Custom(e.into()))?;
+    Ok((player, options))
+}
+
+/// Parse an IIDX board command
+fn board_parser(input: String) -> Result<(BoardOptions,), ParseError> {
+    // The command should satisfy this pattern:
+    // /iidxboard [VERSION] [SP/DP] [dj_points/dan/plays]
+    //
+    // For example:
+    // /iidxboard 29 DP dan
+    let options = input
+        .parse()
+        .map_err(|e: String| ParseError::Custom(e.into()))?;
+    Ok((options,))
+}
+
+/// Parse an IIDX versus command
+fn vs_parser(input: String) -> Result<(u32, String, String, Option<u32>), ParseError> {
+    // The command should satisfy this pattern:
+    // /iidxvs VERSION PLAYER_A PLAYER_B [LEVEL]
+    //
+    // For example:
+    // /iidxvs 28 ORIGIN 1015-0869 12
+    let mut parts = input.split_whitespace();
+    let version = parse_version(Game::Iidx, parts.next())?;
+    let mut player = || {
+        parts
+            .next()
+            .map(str::to_owned)
+            .ok_or_else(|| ParseError::Custom("invalid input".into()))
+    };
+    let (player_a, player_b) = (player()?, player()?);
+    let level = parts
+        .next()
+        .map(|l| next_str_into_u32(Some(l)))
+        .transpose()?;
+    if parts.next().is_some() {
+        return Err(ParseError::Custom("invalid input".into()));
+    }
+    Ok((version, player_a, player_b, level))
+}
+
+/// Parse an IIDX link command
+fn link_parser(input: String) -> Result<(String, Option<u32>), ParseError> {
+    // The command should satisfy this pattern:
+    // /iidxlink [DJ_NAME/IIDX_ID [VERSION]]
+    let mut parts = input.split_whitespace();
+    let param = parts.next().unwrap_or("").to_owned();
+    let version = parts
+        .next()
+        .map(|v| parse_version(Game::Iidx, Some(v)))
+        .transpose()?;
+    Ok((param, version))
+}
+
+/// Parse a profile command of a game other than IIDX
+fn game_profile_parser(game: Game, input: String) -> Result<(Player,), ParseError> {
+    // The command should satisfy this pattern:
+    // /sdvxprofile VERSION NAME/ID
+    match split_player(&input, game, |_| true) {
+        (Some(player), "") => Ok((player,)),
+        _ => Err(ParseError::Custom("invalid input".into())),
+    }
 }
 
-fn split_into_two(input: String) -> Result<(u32, String), ParseError> {
-    let mut parts = input.splitn(2, ' ');
-    Ok((
-        next_str_into_u32(parts.next())?,
-        parts.next().unwrap_or("").to_owned(),
-    ))
+/// Parse a music command of a game other than IIDX
+fn game_music_parser(game: Game, input: String) -> Result<(u32, String), ParseError> {
+    // The command should satisfy this pattern:
+    // /sdvxmusic VERSION TITLE
+    match split_version(&input, game) {
+        (Some(version), title) if !title.is_empty() => Ok((version, title.to_owned())),
+        _ => Err(ParseError::Custom("invalid input".into())),
+    }
+}
+
+/// Parse a recent command of a game other than IIDX
+fn game_recent_parser(game: Game, input: String) -> Result<(Player, u32), ParseError> {
+    // The command should satisfy this pattern:
+    // /sdvxrecent VERSION NAME/ID [N]
+    //
+    // For example:
+    // /sdvxrecent EG ORIGIN 20
+    let (Some(player), rest) = split_player(&input, game, |_| true) else {
+        return Err(ParseError::Custom("invalid input".into()));
+    };
+    let n = match rest {
+        "" => 10,
+        n => next_str_into_u32(Some(n))?,
+    };
+    if n == 0 || n > 50 {
+        return Err(ParseError::Custom("invalid input".into()));
+    }
+    Ok((player, n))
+}
+
+fn sdvx_profile_parser(input: String) -> Result<(Player,), ParseError> {
+    game_profile_parser(Game::Sdvx, input)
+}
+
+fn sdvx_music_parser(input: String) -> Result<(u32, String), ParseError> {
+    game_music_parser(Game::Sdvx, input)
+}
+
+fn sdvx_recent_parser(input: String) -> Result<(Player, u32), ParseError> {
+    game_recent_parser(Game::Sdvx, input)
+}
+
+fn ddr_profile_parser(input: String) -> Result<(Player,), ParseError> {
+    game_profile_parser(Game::Ddr, input)
+}
+
+fn ddr_music_parser(input: String) -> Result<(u32, String), ParseError> {
+    game_music_parser(Game::Ddr, input)
+}
+
+fn ddr_recent_parser(input: String) -> Result<(Player, u32), ParseError> {
+    game_recent_parser(Game::Ddr, input)
+}
+
+/// Parse a BPI command
+fn bpi_parser(input: String) -> Result<(String, Difficulty, u32), ParseError> {
+    // The command should satisfy this pattern:
+    // /bpi TITLE DIFFICULTY EX_SCORE
+    //
+    // For example:
+    // /bpi Verflucht A 3300
+    let mut parts = input.rsplitn(3, ' ');
+    let ex_score = next_str_into_u32(parts.next())?;
+    let difficulty = parts
+        .next()
+        .ok_or_else(|| ParseError::Custom("invalid input".into()))?
+        .parse::<Difficulty>()
+        .map_err(|e| ParseError::Custom(e.into()))?;
+    let title = parts
+        .next()
+        .ok_or_else(|| ParseError::Custom("invalid input".into()))?
+        .to_owned();
+
+    Ok((title, difficulty, ex_score))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_player() {
+        let player = Some(Player {
+            version: 28,
+            param: "ORIGIN".to_owned(),
+        });
+        assert_eq!(
+            best_parser("28 ORIGIN Mind Mapping".to_owned()).unwrap(),
+            (player.clone(), "Mind Mapping".to_owned())
+        );
+        assert_eq!(
+            best_parser("Mind Mapping".to_owned()).unwrap(),
+            (None, "Mind Mapping".to_owned())
+        );
+
+        let (recent_player, options) = recent_parser("28 ORIGIN 20 SP".to_owned()).unwrap();
+        assert_eq!(recent_player, player);
+        assert_eq!(options.n, 20);
+        let (recent_player, options) = recent_parser("20 SP".to_owned()).unwrap();
+        assert_eq!(recent_player, None);
+        assert_eq!(options.n, 20);
+
+        assert_eq!(
+            lamps_parser("28 ORIGIN 12 DP".to_owned()).unwrap(),
+            (player, 12, PlayStyle::Double)
+        );
+        assert_eq!(
+            lamps_parser("12".to_owned()).unwrap(),
+            (None, 12, PlayStyle::Single)
+        );
+        assert!(profile_parser("28".to_owned()).is_err());
+
+        assert_eq!(
+            vs_parser("BISTROVER ORIGIN 1015-0869 12".to_owned()).unwrap(),
+            (28, "ORIGIN".to_owned(), "1015-0869".to_owned(), Some(12))
+        );
+        assert!(vs_parser("28 ORIGIN".to_owned()).is_err());
+
+        let player = Player {
+            version: 6,
+            param: "ORIGIN".to_owned(),
+        };
+        assert_eq!(
+            sdvx_recent_parser("6 ORIGIN 20".to_owned()).unwrap(),
+            (player.clone(), 20)
+        );
+        assert_eq!(
+            sdvx_recent_parser("EXCEED_GEAR ORIGIN".to_owned()).unwrap(),
+            (player, 10)
+        );
+        assert!(sdvx_profile_parser("ORIGIN".to_owned()).is_err());
+        assert!(sdvx_music_parser("Xroniàl Xéro".to_owned()).is_err());
+        assert_eq!(
+            ddr_music_parser("A3 MAX 300".to_owned()).unwrap(),
+            (19, "MAX 300".to_owned())
+        );
+        assert_eq!(
+            music_parser("HV Mind Mapping".to_owned()).unwrap(),
+            (Some(27), "Mind Mapping".to_owned())
+        );
+    }
 }
@@ -0,0 +1,85 @@
+use fuzzy_matcher::{clangd::ClangdMatcher, FuzzyMatcher};
+use priority_queue::PriorityQueue;
+use std::hash::Hash;
+
+fn pop_same_priority<T: PartialEq + Eq + Hash>(pq: &mut PriorityQueue<T, i64>) -> Vec<T> {
+    let mut result = Vec::new();
+    let priority = pq.peek().map(|(_, p)| *p);
+    if let Some(priority) = priority {
+        while let Some((entry, p)) = pq.pop() {
+            if p == priority {
+                result.push(entry);
+            } else {
+                break;
+            }
+        }
+    }
+    result
+}
+
+/// Fuzzy match entries by the key, returning the best matches
+pub fn best_matches<'a, T, F>(entries: &'a [T], pattern: &str, key: F) -> Vec<&'a T>
+where
+    F: Fn(&T) -> &str,
+{
+    let matcher = ClangdMatcher::default();
+    let mut pq: PriorityQueue<usize, i64> = PriorityQueue::new();
+    for (i, entry) in entries.iter().enumerate() {
+        if let Some(score) = matcher.fuzzy_match(key(entry), pattern) {
+            pq.push(i, score);
+        }
+    }
+    pop_same_priority(&mut pq)
+        .into_iter()
+        .map(|i| &entries[i])
+        .collect()
+}
+
+/// Fuzzy match entries by any of the keys, returning at most `n` matches
+/// from the best
+pub fn ranked_matches<'a, T, F, const K: usize>(
+    entries: &'a [T],
+    pattern: &str,
+    keys: F,
+    n: usize,
+) -> Vec<&'a T>
+where
+    F: Fn(&T) -> [&str; K],
+{
+    let matcher = ClangdMatcher::default();
+    let mut pq: PriorityQueue<usize, i64> = PriorityQueue::new();
+    for (i, entry) in entries.iter().enumerate() {
+        if let Some(score) = keys(entry)
+            .iter()
+            .filter_map(|key| matcher.fuzzy_match(key, pattern))
+            .max()
+        {
+            pq.push(i, score);
+        }
+    }
+    pq.into_sorted_iter()
+        .take(n)
+        .map(|(i, _)| &entries[i])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ranked_matches() {
+        let songs = [
+            ("Mind Mapping", "Ryu*"),
+            ("Starlight Vision", "Mind Mapper"),
+            ("Rave It!! Rave It!!", "DJ TOTTO"),
+        ];
+        let matches = ranked_matches(&songs, "mind", |s| [s.0, s.1], 5);
+        assert_eq!(matches.len(), 2);
+        assert!(matches.contains(&&songs[0]));
+        assert!(matches.contains(&&songs[1]));
+
+        let matches = ranked_matches(&songs, "totto", |s| [s.0, s.1], 1);
+        assert_eq!(matches, vec![&songs[2]]);
+    }
+}
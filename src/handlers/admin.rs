@@ -0,0 +1,11 @@
+use teloxide::prelude::*;
+
+use crate::ADMINS;
+
+/// Whether the message is sent by a bot admin
+pub fn is_admin(message: &Message) -> bool {
+    message
+        .from
+        .as_ref()
+        .is_some_and(|user| ADMINS.contains(&user.id.0))
+}
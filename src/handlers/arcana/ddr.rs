@@ -0,0 +1,123 @@
+use teloxide::utils::markdown::*;
+
+use crate::{
+    arcana::ddr::{Chart, Music, Profile, ScoreHistory},
+    handlers::ddr_score_calc::rank,
+};
+
+pub fn render_profile(p: &Profile) -> String {
+    format!(
+        "DANCER NAME: {}\nDDR ID: {}",
+        escape(&p.name),
+        escape(&p.ddr_id)
+    )
+}
+
+pub fn render_music(m: &Music, charts: &[Chart]) -> String {
+    let mut charts = charts.iter().collect::<Vec<_>>();
+    charts.sort_by_key(|c| (c.play_style, c.rating));
+    let mut table = format!("{:<13} {:>2}", "", "LV");
+    for c in charts {
+        table.push_str(&format!(
+            "\n{:<13} {:>2}",
+            format!("{} {}", c.play_style, c.difficulty),
+            c.rating
+        ));
+    }
+
+    format!(
+        "{}\n{}\n{}",
+        bold(&escape(&m.title)),
+        escape(&m.artist),
+        code_block(&table)
+    )
+}
+
+pub fn render_play(play: &ScoreHistory, music: Option<&Music>, chart: Option<&Chart>) -> String {
+    let title = music.map_or(play.music_id.as_str(), |m| m.title.as_str());
+    let chart = chart.map_or(String::new(), |c| {
+        format!(" [{} {} {}]", c.play_style, c.difficulty, c.rating)
+    });
+    format!(
+        "{}\n{}",
+        bold(&escape(&format!("{}{}", title, chart))),
+        escape(&format!(
+            "{} {} EX {} {} ({})",
+            play.score,
+            rank(play.score),
+            play.ex_score.map_or("-".to_owned(), |ex| ex.to_string()),
+            play.lamp.replace('_', " "),
+            play.timestamp.split('T').next().unwrap_or("")
+        ))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arcana::PlayStyle;
+
+    fn chart(play_style: PlayStyle, difficulty: &str, rating: u32) -> Chart {
+        Chart {
+            id: format!("{}{}", play_style, difficulty),
+            difficulty: difficulty.to_owned(),
+            music_id: "Lm4tQ8wZr1c".to_owned(),
+            play_style,
+            rating,
+        }
+    }
+
+    #[test]
+    fn test_render_music() {
+        let m = Music {
+            id: "Lm4tQ8wZr1c".to_owned(),
+            artist: "TAG".to_owned(),
+            title: "PARANOiA Revolution".to_owned(),
+        };
+        let charts = [
+            chart(PlayStyle::Double, "EXPERT", 17),
+            chart(PlayStyle::Single, "CHALLENGE", 18),
+            chart(PlayStyle::Single, "EXPERT", 16),
+        ];
+
+        let output = render_music(&m, &charts);
+        assert!(output.contains("SP EXPERT     16\nSP CHALLENGE  18\nDP EXPERT     17"));
+    }
+
+    #[test]
+    fn test_render_play() {
+        let play = ScoreHistory {
+            id: "Wc5nR2kJp8d".to_owned(),
+            chart_id: "SPCHALLENGE".to_owned(),
+            ex_score: Some(2891),
+            lamp: "GREAT_FULL_COMBO".to_owned(),
+            music_id: "Lm4tQ8wZr1c".to_owned(),
+            profile_id: "Zt7uB3yHq6e".to_owned(),
+            score: 991_230,
+            timestamp: "2023-02-11T15:20:03Z".to_owned(),
+        };
+        let music = Music {
+            id: "Lm4tQ8wZr1c".to_owned(),
+            artist: "TAG".to_owned(),
+            title: "PARANOiA Revolution".to_owned(),
+        };
+
+        let output = render_play(
+            &play,
+            Some(&music),
+            Some(&chart(PlayStyle::Single, "CHALLENGE", 18)),
+        );
+        assert!(output.contains("*PARANOiA Revolution \\[SP CHALLENGE 18\\]*"));
+        assert!(output.contains("991230 AAA EX 2891 GREAT FULL COMBO \\(2023\\-02\\-11\\)"));
+
+        let output = render_play(
+            &ScoreHistory {
+                ex_score: None,
+                ..play
+            },
+            None,
+            None,
+        );
+        assert!(output.contains("*Lm4tQ8wZr1c*\n991230 AAA EX \\- GREAT FULL COMBO"));
+    }
+}
@@ -0,0 +1,85 @@
+use std::{collections::HashMap, error::Error};
+use teloxide::{prelude::*, utils::markdown::*};
+
+use super::{music::search, resolve_profile, send_output, Output, Player, Resolved};
+use crate::{
+    arcana::{
+        iidx::{get_best, Best, Chart, Music},
+        Result,
+    },
+    handlers::arcana::ArcanaContext,
+};
+
+/// Render the personal bests on each played chart of the song
+fn render_best(m: &Music, charts: &[Chart], bests: &HashMap<String, Best>) -> String {
+    let mut charts = charts.iter().collect::<Vec<_>>();
+    charts.sort_by_key(|c| (c.play_style, c.difficulty));
+
+    let played = charts
+        .iter()
+        .filter_map(|c| {
+            let best = bests.get(&c.id)?;
+            let date = |timestamp: &str| timestamp.split('T').next().unwrap_or("").to_owned();
+            Some(format!(
+                "{}\n{}",
+                bold(&escape(&format!(
+                    "{} {} {}",
+                    c.play_style, c.difficulty, c.rating
+                ))),
+                escape(&format!(
+                    "EX Score: {} ({})\nLamp: {} ({})\nMiss Count: {}",
+                    best.ex_score.value,
+                    date(&best.ex_score.timestamp),
+                    best.lamp.value,
+                    date(&best.lamp.timestamp),
+                    best.miss_count
+                        .as_ref()
+                        .map(|m| format!("{} ({})", m.value, date(&m.timestamp)))
+                        .unwrap_or("-".to_owned()),
+                ))
+            ))
+        })
+        .collect::<Vec<String>>();
+
+    format!(
+        "{}\n{}\n\n{}",
+        bold(&escape(&m.title)),
+        escape(&m.artist),
+        if played.is_empty() {
+            "No plays".to_owned()
+        } else {
+            played.join("\n\n")
+        }
+    )
+}
+
+async fn best_output(
+    ctx: &ArcanaContext,
+    message: &Message,
+    player: Option<Player>,
+    title: &str,
+) -> Result<Output> {
+    let (version, p) = match resolve_profile(ctx, message.from.as_ref(), player).await? {
+        Resolved::Profile(version, p) => (version, p),
+        Resolved::Reply(reply) => return Ok(reply),
+    };
+    // take the best match when several songs match the title
+    let Some(m) = search(ctx, version, title).await?.into_iter().next() else {
+        return Ok(("Song not found".to_owned(), None));
+    };
+    let charts = ctx.cache.charts(&ctx.client, version, &m.id).await?;
+    let bests = get_best(&ctx.client, version, &p.id, &m.id).await?;
+
+    Ok((render_best(&m, &charts, &bests), None))
+}
+
+pub async fn best(
+    bot: Bot,
+    message: Message,
+    ctx: &ArcanaContext,
+    player: Option<Player>,
+    title: &str,
+) -> std::result::Result<(), Box<dyn Error + Send + Sync>> {
+    let output = best_output(ctx, &message, player, title).await;
+    send_output(bot, &message, output).await
+}
@@ -0,0 +1,337 @@
+use std::{cmp::Ordering, error::Error, fmt, str::FromStr};
+use teloxide::{
+    prelude::*,
+    types::{ChatId, UserId},
+    utils::markdown::*,
+};
+
+use crate::{
+    arcana::{
+        iidx::{DanRank, Profile, Score},
+        Game, PlayStyle, Result,
+    },
+    handlers::arcana::{reply, ArcanaContext},
+    links::Link,
+    DEFAULT_IIDX_VERSION,
+};
+
+/// Stat players are ranked by on `/iidxboard`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoardOrder {
+    DjPoints,
+    Dan,
+    Plays,
+}
+
+impl fmt::Display for BoardOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BoardOrder::DjPoints => write!(f, "dj_points"),
+            BoardOrder::Dan => write!(f, "dan"),
+            BoardOrder::Plays => write!(f, "plays"),
+        }
+    }
+}
+
+impl FromStr for BoardOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().replace(' ', "_").as_str() {
+            "dj_points" | "djpoints" | "points" => Ok(BoardOrder::DjPoints),
+            "dan" | "rank" => Ok(BoardOrder::Dan),
+            "plays" => Ok(BoardOrder::Plays),
+            _ => Err(format!("invalid order: {}", s)),
+        }
+    }
+}
+
+impl BoardOrder {
+    fn header(&self) -> &'static str {
+        match self {
+            BoardOrder::DjPoints => "DJ POINTS",
+            BoardOrder::Dan => "DAN",
+            BoardOrder::Plays => "PLAYS",
+        }
+    }
+
+    fn value(&self, score: &Score) -> String {
+        match self {
+            BoardOrder::DjPoints => score.dj_points.to_string(),
+            BoardOrder::Dan => score
+                .rank
+                .as_ref()
+                .map_or("-".to_owned(), |r| r.english().to_owned()),
+            BoardOrder::Plays => score.plays.to_string(),
+        }
+    }
+
+    /// Compare the stats, the higher first
+    fn cmp(&self, a: &Score, b: &Score) -> Ordering {
+        match self {
+            BoardOrder::DjPoints => b.dj_points.cmp(&a.dj_points),
+            BoardOrder::Dan => dan_position(b).cmp(&dan_position(a)),
+            BoardOrder::Plays => b.plays.cmp(&a.plays),
+        }
+    }
+}
+
+/// Where the dan rank goes on a board, as ranks unknown to the bot cannot be
+/// compared with the known ones: known ranks first, then unknown ones, then
+/// no rank
+fn dan_position(score: &Score) -> (u8, Option<&DanRank>) {
+    match &score.rank {
+        Some(DanRank::Other(_)) => (1, score.rank.as_ref()),
+        Some(_) => (2, score.rank.as_ref()),
+        None => (0, None),
+    }
+}
+
+/// Version, play style and order of `/iidxboard`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoardOptions {
+    /// Version to rank players in, otherwise the linked version of the user
+    pub version: Option<u32>,
+    pub play_style: PlayStyle,
+    pub order: BoardOrder,
+}
+
+impl Default for BoardOptions {
+    fn default() -> Self {
+        Self {
+            version: None,
+            play_style: PlayStyle::Single,
+            order: BoardOrder::DjPoints,
+        }
+    }
+}
+
+impl FromStr for BoardOptions {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut options = BoardOptions::default();
+        for token in s.split_whitespace() {
+            if let Ok(play_style) = token.parse() {
+                options.play_style = play_style;
+            } else if let Ok(order) = token.parse() {
+                options.order = order;
+            } else if let Some(version) = Game::Iidx.parse_version(token) {
+                options.version = Some(version);
+            } else {
+                return Err(format!("invalid option: {}", token));
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Sort the players by the stat of the play style, breaking ties by DJ
+/// POINTS and then the DJ name
+fn rank_players(players: &mut [(UserId, Profile)], options: &BoardOptions) {
+    players.sort_by(|(_, a), (_, b)| {
+        let (sa, sb) = (a.score(options.play_style), b.score(options.play_style));
+        options
+            .order
+            .cmp(sa, sb)
+            .then_with(|| BoardOrder::DjPoints.cmp(sa, sb))
+            .then_with(|| a.dj_name.cmp(&b.dj_name))
+    });
+}
+
+/// Render the ranked players with how their positions changed since the
+/// positions given
+fn render_board(
+    title: &str,
+    players: &[(UserId, Profile)],
+    previous: &[Option<usize>],
+    options: &BoardOptions,
+) -> String {
+    let mut table = format!("{:>2} {:<6} {:>9}", "#", "NAME", options.order.header());
+    for (position, ((_, p), previous)) in players.iter().zip(previous).enumerate() {
+        let position = position + 1;
+        let change = match previous {
+            None => "NEW".to_owned(),
+            Some(previous) => match previous.cmp(&position) {
+                Ordering::Greater => format!("↑{}", previous - position),
+                Ordering::Less => format!("↓{}", position - previous),
+                Ordering::Equal => String::new(),
+            },
+        };
+        let row = format!(
+            "{:>2} {:<6} {:>9} {}",
+            position,
+            p.dj_name,
+            options.order.value(p.score(options.play_style)),
+            change
+        );
+        table.push('\n');
+        table.push_str(row.trim_end());
+    }
+
+    format!("{}\n{}", bold(&escape(title)), code_block(&table))
+}
+
+async fn board_output(
+    ctx: &ArcanaContext,
+    chat_id: ChatId,
+    version: u32,
+    members: Vec<(UserId, Link)>,
+    options: &BoardOptions,
+) -> Result<String> {
+    if members.is_empty() {
+        return Ok(escape(
+            "No one here has linked a profile yet, link yours with /iidxlink DJ_NAME/IIDX_ID.",
+        ));
+    }
+    let mut players = Vec::new();
+    let mut absent = 0;
+    for (user, link) in members {
+        // players are looked up by IIDX ID whichever version they linked
+        match ctx
+            .cache
+            .profile(&ctx.client, version, &link.iidx_id)
+            .await?
+        {
+            Some(profile) => players.push((user, profile)),
+            None => absent += 1,
+        }
+    }
+    rank_players(&mut players, options);
+
+    let board = format!(
+        "{}:{}:{}:{}",
+        chat_id, version, options.play_style, options.order
+    );
+    let positions = players
+        .iter()
+        .enumerate()
+        .map(|(position, (user, _))| (*user, position + 1))
+        .collect::<Vec<_>>();
+    let previous = ctx.links.reposition(&board, &positions).await;
+
+    let mut output = render_board(
+        &format!(
+            "IIDX {} {} board by {}",
+            Game::Iidx.version_name(version),
+            options.play_style,
+            options.order.header()
+        ),
+        &players,
+        &previous,
+        options,
+    );
+    if absent > 0 {
+        output.push_str(&escape(&format!(
+            "\n{} linked player(s) here have not played this version.",
+            absent
+        )));
+    }
+    Ok(output)
+}
+
+/// Rank the players in the chat who linked their profiles
+pub async fn board(
+    bot: Bot,
+    message: Message,
+    ctx: &ArcanaContext,
+    options: BoardOptions,
+) -> std::result::Result<(), Box<dyn Error + Send + Sync>> {
+    let mut members = Vec::new();
+    // only those who have used the bot here are checked, as they may have left
+    for (user, link) in ctx.links.in_chat(message.chat.id).await {
+        let present = message.chat.is_private()
+            || bot
+                .get_chat_member(message.chat.id, user)
+                .await
+                .is_ok_and(|member| member.is_present());
+        if present {
+            members.push((user, link));
+        }
+    }
+    let version = match options.version {
+        Some(version) => version,
+        None => match message.from.as_ref() {
+            Some(from) => ctx
+                .links
+                .get(from.id)
+                .await
+                .map_or(*DEFAULT_IIDX_VERSION, |link| link.version),
+            None => *DEFAULT_IIDX_VERSION,
+        },
+    };
+    let output = board_output(ctx, message.chat.id, version, members, &options).await;
+    reply(bot, message, output).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_board() {
+        assert_eq!(
+            "dan DP".parse(),
+            Ok(BoardOptions {
+                version: None,
+                play_style: PlayStyle::Double,
+                order: BoardOrder::Dan,
+            })
+        );
+        assert_eq!(
+            "29 plays".parse(),
+            Ok(BoardOptions {
+                version: Some(29),
+                play_style: PlayStyle::Single,
+                order: BoardOrder::Plays,
+            })
+        );
+        assert_eq!("".parse(), Ok(BoardOptions::default()));
+        assert!("SP lamps".parse::<BoardOptions>().is_err());
+
+        let profile = |dj_name: &str, dj_points, rank| {
+            let score = Score {
+                dj_points,
+                plays: 100,
+                rank,
+            };
+            Profile {
+                id: dj_name.to_owned(),
+                dj_name: dj_name.to_owned(),
+                iidx_id: dj_name.to_owned(),
+                sp: score.clone(),
+                dp: score,
+            }
+        };
+        let mut players = vec![
+            (UserId(1), profile("ALPHA", 1200, Some(DanRank::Dan8))),
+            (UserId(2), profile("BRAVO", 1532, Some(DanRank::Dan7))),
+            (UserId(3), profile("CHARLY", 900, None)),
+            (UserId(4), profile("DELTA", 1000, Some(DanRank::Dan8))),
+            (
+                UserId(5),
+                profile("ECHO", 1100, Some(DanRank::Other("十一段".to_owned()))),
+            ),
+        ];
+        let options = BoardOptions {
+            order: BoardOrder::Dan,
+            ..Default::default()
+        };
+        rank_players(&mut players, &options);
+        assert_eq!(
+            players.iter().map(|(user, _)| user.0).collect::<Vec<_>>(),
+            vec![1, 4, 2, 5, 3]
+        );
+
+        let output = render_board(
+            "Board",
+            &players,
+            &[Some(2), Some(2), None, Some(4), Some(4)],
+            &options,
+        );
+        assert!(output.contains(" 1 ALPHA    8th Dan ↑1\n 2 DELTA    8th Dan\n"));
+        assert!(output.contains(
+            " 3 BRAVO    7th Dan NEW\n 4 ECHO         十一段\n 5 CHARLY         - ↓1\n```"
+        ));
+    }
+}
@@ -0,0 +1,152 @@
+use std::error::Error;
+use teloxide::{prelude::*, utils::markdown::*};
+
+use super::{resolve_profile, send_output, Output, Player, Resolved};
+use crate::{
+    arcana::{
+        iidx::{best_by_chart, get_level_charts, get_score_history, Difficulty},
+        PlayStyle, Result,
+    },
+    bpi::{calc_bpi, total_bpi, BpiChart, BpiTable, MIN_BPI},
+    fuzzy::best_matches,
+    handlers::arcana::ArcanaContext,
+};
+
+/// Level of the charts in the BPI reference data
+const BPI_LEVEL: u32 = 12;
+
+/// Fold full-width characters and case, which Arcana and the reference data
+/// do not always agree on
+fn normalize(title: &str) -> String {
+    title
+        .chars()
+        .map(|c| match c {
+            '\u{ff01}'..='\u{ff5e}' => char::from_u32(c as u32 - 0xfee0).unwrap_or(c),
+            '\u{3000}' => ' ',
+            '\u{301c}' => '~',
+            _ => c,
+        })
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Reference data of the chart, matching the title exactly if possible,
+/// otherwise by the only best fuzzy match
+fn find_reference<'a>(
+    table: &'a [BpiChart],
+    title: &str,
+    difficulty: Difficulty,
+) -> Option<&'a BpiChart> {
+    let title = normalize(title);
+    let charts = table
+        .iter()
+        .filter(|c| c.difficulty == difficulty)
+        .map(|c| (normalize(&c.title), c))
+        .collect::<Vec<_>>();
+    if let Some((_, chart)) = charts.iter().find(|(t, _)| *t == title) {
+        return Some(chart);
+    }
+    match best_matches(&charts, &title, |(t, _)| t)[..] {
+        [(_, chart)] => Some(chart),
+        _ => None,
+    }
+}
+
+async fn bpi_total_output(
+    ctx: &ArcanaContext,
+    table: &BpiTable,
+    message: &Message,
+    player: Option<Player>,
+) -> Result<Output> {
+    let (version, p) = match resolve_profile(ctx, message.from.as_ref(), player).await? {
+        Resolved::Profile(version, p) => (version, p),
+        Resolved::Reply(reply) => return Ok(reply),
+    };
+    let charts = get_level_charts(&ctx.client, version, PlayStyle::Single, BPI_LEVEL).await?;
+    let bests = best_by_chart(&get_score_history(&ctx.client, version, &p.id).await?);
+    let catalogue = ctx.cache.catalogue(&ctx.client, version).await?;
+
+    let table = table.read().await;
+    let mut bpis = Vec::new();
+    let mut played = 0;
+    for chart in charts {
+        let Some(m) = catalogue.iter().find(|m| m.id == chart.music_id) else {
+            continue;
+        };
+        // charts without reference data are left out of the total
+        let Some(reference) = find_reference(&table, &m.title, chart.difficulty) else {
+            continue;
+        };
+        let bpi = match bests.get(&chart.id) {
+            Some(best) => {
+                played += 1;
+                calc_bpi(reference, best.ex_score.value).unwrap_or(MIN_BPI)
+            }
+            None => MIN_BPI,
+        };
+        bpis.push(bpi);
+    }
+
+    let Some(total) = total_bpi(&bpis) else {
+        return Ok((escape("No SP12 charts with BPI reference data"), None));
+    };
+    Ok((
+        format!(
+            "{}\n{}",
+            bold(&escape(&format!("{} ({})", p.dj_name, p.iidx_id))),
+            escape(&format!(
+                "Total BPI: {:.2}\nSP12 charts played: {}/{}",
+                total,
+                played,
+                bpis.len()
+            ))
+        ),
+        None,
+    ))
+}
+
+/// Calculate the total BPI over the SP12 personal bests of a profile
+pub async fn bpi_total(
+    bot: Bot,
+    message: Message,
+    ctx: &ArcanaContext,
+    table: &BpiTable,
+    player: Option<Player>,
+) -> std::result::Result<(), Box<dyn Error + Send + Sync>> {
+    let output = bpi_total_output(ctx, table, &message, player).await;
+    send_output(bot, &message, output).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_reference() {
+        let chart = |title: &str, difficulty| BpiChart {
+            title: title.to_owned(),
+            difficulty,
+            notes: 1000,
+            wr: 1900,
+            avg: 1700,
+            coef: None,
+        };
+        let table = [
+            chart("Mind Mapping", Difficulty::Another),
+            chart("Mind Mapping", Difficulty::Hyper),
+            chart("quell～the seventh slave～", Difficulty::Another),
+            chart("quell", Difficulty::Leggendaria),
+        ];
+
+        let find = |title, difficulty| find_reference(&table, title, difficulty);
+        assert_eq!(find("MIND MAPPING", Difficulty::Another), Some(&table[0]));
+        assert_eq!(find("Mind Mapping", Difficulty::Hyper), Some(&table[1]));
+        assert_eq!(
+            find("quell~the seventh slave~", Difficulty::Another),
+            Some(&table[2])
+        );
+        assert_eq!(find("quell", Difficulty::Another), Some(&table[2]));
+        assert_eq!(find("Mind Mapping", Difficulty::Normal), None);
+        assert_eq!(find("Rave It!! Rave It!!", Difficulty::Another), None);
+    }
+}
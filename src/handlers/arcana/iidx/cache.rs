@@ -0,0 +1,50 @@
+use std::error::Error;
+use teloxide::{prelude::*, types::ReplyParameters};
+
+use crate::{
+    arcana::Game,
+    handlers::{admin::is_admin, arcana::ArcanaContext},
+};
+
+/// Warm or invalidate the metadata cache (admin only)
+pub async fn cache(
+    bot: Bot,
+    message: Message,
+    ctx: &ArcanaContext,
+    action: &str,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if !is_admin(&message) {
+        bot.send_message(message.chat.id, "Permission denied!")
+            .reply_parameters(ReplyParameters::new(message.id))
+            .await?;
+        return Ok(());
+    }
+
+    let mut parts = action.split_whitespace();
+    let output = match (
+        parts.next(),
+        parts.next().map(|v| Game::Iidx.parse_version(v)),
+    ) {
+        (Some("warm"), Some(Some(version))) => match ctx.cache.warm(&ctx.client, version).await {
+            Ok((music, charts)) => format!(
+                "Cached {} music and {} charts of version {}!",
+                music, charts, version
+            ),
+            Err(e) => format!("Failed to warm the cache: {}", e),
+        },
+        (Some("invalidate"), None) => {
+            ctx.cache.invalidate(None).await;
+            "Invalidated all versions!".to_owned()
+        }
+        (Some("invalidate"), Some(Some(version))) => {
+            ctx.cache.invalidate(Some(version)).await;
+            format!("Invalidated version {}!", version)
+        }
+        _ => "Usage: /iidxcache warm VERSION or /iidxcache invalidate [VERSION]".to_owned(),
+    };
+    bot.send_message(message.chat.id, output)
+        .reply_parameters(ReplyParameters::new(message.id))
+        .await?;
+
+    Ok(())
+}
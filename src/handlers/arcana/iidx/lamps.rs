@@ -0,0 +1,158 @@
+use std::{collections::HashMap, error::Error};
+use teloxide::{prelude::*, utils::markdown::*};
+
+use super::{resolve_profile, send_output, Output, Player, Resolved};
+use crate::{
+    arcana::{
+        iidx::{
+            best_by_chart, best_lamps, get_level_charts, get_score_history, Chart, Lamp, Music,
+            LAMPS,
+        },
+        PlayStyle, Result,
+    },
+    handlers::arcana::ArcanaContext,
+};
+
+/// Maximum number of uncleared charts to list
+const MAX_UNCLEARED: usize = 30;
+
+/// Render the lamp counts and the uncleared charts
+fn render_lamps(
+    header: &str,
+    lamps: &[(&Chart, Lamp)],
+    catalogue: &HashMap<String, Music>,
+) -> String {
+    let counts = LAMPS
+        .iter()
+        .map(|lamp| {
+            format!(
+                "{:<13} {:>3}",
+                lamp.to_string(),
+                lamps.iter().filter(|(_, l)| l == lamp).count()
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let mut uncleared = lamps
+        .iter()
+        .filter(|(_, lamp)| *lamp < Lamp::AssistClear)
+        .map(|(c, lamp)| {
+            format!(
+                "{} [{}] {}",
+                catalogue
+                    .get(&c.music_id)
+                    .map_or(c.music_id.as_str(), |m| m.title.as_str()),
+                c.difficulty.short(),
+                lamp
+            )
+        })
+        .collect::<Vec<String>>();
+    uncleared.sort();
+    let more = uncleared.len().saturating_sub(MAX_UNCLEARED);
+    uncleared.truncate(MAX_UNCLEARED);
+    if more > 0 {
+        uncleared.push(format!("and {} more", more));
+    }
+
+    format!(
+        "{}\n{}\n{}",
+        bold(&escape(header)),
+        code_block(&counts),
+        if uncleared.is_empty() {
+            escape("All cleared!")
+        } else {
+            format!(
+                "{}\n{}",
+                bold("Not cleared:"),
+                escape(&uncleared.join("\n"))
+            )
+        }
+    )
+}
+
+async fn lamps_output(
+    ctx: &ArcanaContext,
+    message: &Message,
+    player: Option<Player>,
+    level: u32,
+    play_style: PlayStyle,
+) -> Result<Output> {
+    let (version, p) = match resolve_profile(ctx, message.from.as_ref(), player).await? {
+        Resolved::Profile(version, p) => (version, p),
+        Resolved::Reply(reply) => return Ok(reply),
+    };
+    let charts = get_level_charts(&ctx.client, version, play_style, level).await?;
+    if charts.is_empty() {
+        return Ok((
+            escape(&format!("No {} LV{} charts", play_style, level)),
+            None,
+        ));
+    }
+    let bests = best_by_chart(&get_score_history(&ctx.client, version, &p.id).await?);
+    let catalogue = ctx
+        .cache
+        .catalogue(&ctx.client, version)
+        .await?
+        .into_iter()
+        .map(|m| (m.id.clone(), m))
+        .collect();
+
+    Ok((
+        render_lamps(
+            &format!("{} {} LV{}", p.dj_name, play_style, level),
+            &best_lamps(&charts, &bests),
+            &catalogue,
+        ),
+        None,
+    ))
+}
+
+pub async fn lamps(
+    bot: Bot,
+    message: Message,
+    ctx: &ArcanaContext,
+    player: Option<Player>,
+    level: u32,
+    play_style: PlayStyle,
+) -> std::result::Result<(), Box<dyn Error + Send + Sync>> {
+    let output = lamps_output(ctx, &message, player, level, play_style).await;
+    send_output(bot, &message, output).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arcana::iidx::Difficulty;
+
+    #[test]
+    fn test_render_lamps() {
+        let chart = |id: &str, difficulty| Chart {
+            id: id.to_owned(),
+            music_id: "G6vGmV2XC2Y".to_owned(),
+            difficulty,
+            ..Default::default()
+        };
+        let charts = [
+            chart("8bWq2LkPz0x", Difficulty::Another),
+            chart("3rN9ctmLu3V", Difficulty::Hyper),
+        ];
+        let lamps = [(&charts[0], Lamp::HardClear), (&charts[1], Lamp::Failed)];
+        let catalogue = HashMap::from([(
+            "G6vGmV2XC2Y".to_owned(),
+            Music {
+                id: "G6vGmV2XC2Y".to_owned(),
+                artist: "Ryu*".to_owned(),
+                folder: 28,
+                genre: "HI-TECH".to_owned(),
+                title: "Mind Mapping".to_owned(),
+            },
+        )]);
+
+        let output = render_lamps("ORIGIN SP LV12", &lamps, &catalogue);
+        assert!(output.contains("HARD CLEAR      1"));
+        assert!(output.contains("NO PLAY         0"));
+        assert!(output.contains("Mind Mapping \\[H\\] FAILED"));
+        assert!(!output.contains("[A]"));
+    }
+}
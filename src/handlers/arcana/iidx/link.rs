@@ -0,0 +1,171 @@
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+};
+use teloxide::{
+    prelude::*,
+    types::{
+        InlineKeyboardButton, InlineKeyboardMarkup, MaybeInaccessibleMessage, ParseMode, User,
+    },
+    utils::markdown::*,
+};
+
+use super::{candidate, get_profiles, send_output, Output};
+use crate::{
+    arcana::{
+        iidx::{get_profile_by_id, Profile},
+        Game, Result,
+    },
+    handlers::{
+        arcana::{error_reply, ArcanaContext},
+        callback::Callback,
+    },
+    links::Link,
+    progress::Snapshot,
+    DEFAULT_IIDX_VERSION,
+};
+
+fn describe(dj_name: &str, iidx_id: &str, version: u32) -> String {
+    format!(
+        "DJ {} ({}) on version {}",
+        dj_name,
+        iidx_id,
+        Game::Iidx.version_name(version)
+    )
+}
+
+async fn link_output(
+    ctx: &ArcanaContext,
+    user: &User,
+    param: &str,
+    version: Option<u32>,
+) -> Result<Output> {
+    if param.is_empty() {
+        let output = match ctx.links.get(user.id).await {
+            Some(link) => format!(
+                "Linked to {}.",
+                describe(&link.dj_name, &link.iidx_id, link.version)
+            ),
+            None => "Not linked yet. Usage: /iidxlink DJ_NAME/IIDX_ID [VERSION]".to_owned(),
+        };
+        return Ok((escape(&output), None));
+    }
+
+    let version = version.unwrap_or(*DEFAULT_IIDX_VERSION);
+    let profiles = get_profiles(&ctx.client, version, param).await?;
+    let confirm = |p: &Profile, text: String| {
+        InlineKeyboardButton::callback(
+            text,
+            Callback::Link {
+                user: user.id,
+                version,
+                profile_id: p.id.clone(),
+            }
+            .to_string(),
+        )
+    };
+    let cancel = InlineKeyboardButton::callback(
+        "Cancel",
+        Callback::CancelLink { user: user.id }.to_string(),
+    );
+    let (output, keyboard) = match profiles.as_slice() {
+        [] => return Ok(("Not found".to_owned(), None)),
+        [p] => (
+            format!(
+                "Link your account to {}?",
+                describe(&p.dj_name, &p.iidx_id, version)
+            ),
+            InlineKeyboardMarkup::new([[confirm(p, "Confirm".to_owned()), cancel]]),
+        ),
+        // several profiles share the DJ name, so let the user pick one
+        profiles => (
+            format!(
+                "Several profiles match on version {}, pick one to link:",
+                Game::Iidx.version_name(version)
+            ),
+            InlineKeyboardMarkup::new(
+                profiles
+                    .iter()
+                    .map(|p| vec![confirm(p, candidate(p))])
+                    .chain([vec![cancel]]),
+            ),
+        ),
+    };
+
+    Ok((escape(&output), Some(keyboard)))
+}
+
+/// Link the Telegram user to an Arcana profile after confirmation
+pub async fn link(
+    bot: Bot,
+    message: Message,
+    ctx: &ArcanaContext,
+    param: &str,
+    version: Option<u32>,
+) -> std::result::Result<(), Box<dyn Error + Send + Sync>> {
+    let Some(user) = message.from.as_ref() else {
+        return Ok(());
+    };
+    let output = link_output(ctx, user, param, version).await;
+    send_output(bot, &message, output).await
+}
+
+async fn confirm_output(
+    ctx: &ArcanaContext,
+    user: &User,
+    chat_id: ChatId,
+    version: u32,
+    profile_id: &str,
+) -> Result<String> {
+    let Some(p) = get_profile_by_id(&ctx.client, version, profile_id).await? else {
+        return Ok("Not found".to_owned());
+    };
+    let output = format!("Linked to {}!", describe(&p.dj_name, &p.iidx_id, version));
+    let snapshot = Snapshot::new(version, &p);
+    ctx.links
+        .set(
+            user.id,
+            Link {
+                version,
+                profile_id: p.id,
+                iidx_id: p.iidx_id,
+                dj_name: p.dj_name,
+                notify: None,
+                history: vec![snapshot],
+                positions: HashMap::new(),
+                chats: HashSet::from([chat_id]),
+            },
+        )
+        .await;
+
+    Ok(escape(&output))
+}
+
+/// Store the link confirmed by the user
+pub async fn link_confirmed(
+    bot: Bot,
+    message: &MaybeInaccessibleMessage,
+    ctx: &ArcanaContext,
+    user: &User,
+    version: u32,
+    profile_id: &str,
+) -> std::result::Result<(), Box<dyn Error + Send + Sync>> {
+    let output = confirm_output(ctx, user, message.chat().id, version, profile_id)
+        .await
+        .unwrap_or_else(|e| error_reply(&e));
+    bot.edit_message_text(message.chat().id, message.id(), output)
+        .parse_mode(ParseMode::MarkdownV2)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn link_cancelled(
+    bot: Bot,
+    message: &MaybeInaccessibleMessage,
+) -> std::result::Result<(), Box<dyn Error + Send + Sync>> {
+    bot.edit_message_text(message.chat().id, message.id(), "Cancelled.")
+        .await?;
+
+    Ok(())
+}
@@ -1,24 +1,239 @@
 use std::error::Error;
+use teloxide::{
+    prelude::*,
+    types::{
+        InlineKeyboardButton, InlineKeyboardMarkup, MaybeInaccessibleMessage, ParseMode,
+        ReplyParameters, User,
+    },
+    utils::{command::BotCommands, markdown::escape},
+};
 
-use crate::arcana::iidx::{get_profile, get_profile_using_id, Profile};
+use crate::{
+    arcana::{
+        find_profiles,
+        iidx::{get_profile_by_id, get_profile_using_id, Profile},
+        ArcanaClient, Result,
+    },
+    bpi::BpiTable,
+    commands::Command,
+    handlers::{
+        arcana::{error_reply, ArcanaContext, Player},
+        callback::Callback,
+    },
+    links::Link,
+};
 
-async fn get_profiles(
-    version: u32,
-    param: &str,
-) -> Result<Vec<Profile>, Box<dyn Error + Send + Sync>> {
-    let dj_name_profiles = get_profile(version, param).await?;
+/// MarkdownV2 output of a command with its inline keyboard, if any
+pub type Output = (String, Option<InlineKeyboardMarkup>);
+
+/// Profile a command is about, or the reply when there is none or several
+pub enum Resolved {
+    Profile(u32, Profile),
+    Reply(Output),
+}
+
+/// Short description of a profile to tell it from others sharing the DJ
+/// name, e.g. `ORIGIN (1234-5678) 七段/-`
+fn candidate(p: &Profile) -> String {
+    format!(
+        "{} ({}) {}/{}",
+        p.dj_name,
+        p.iidx_id,
+        p.sp.rank.as_ref().map_or("-", |r| r.japanese()),
+        p.dp.rank.as_ref().map_or("-", |r| r.japanese())
+    )
+}
+
+/// Ask for the IIDX ID of a player matching several profiles
+fn ambiguous_player(player: &str, profiles: &[Profile]) -> String {
+    escape(&format!(
+        "Several profiles match {}, please give the IIDX ID:\n{}",
+        player,
+        profiles
+            .iter()
+            .map(candidate)
+            .collect::<Vec<String>>()
+            .join("\n")
+    ))
+}
+
+/// Ask the user to pick one of the profiles matching the player, or to give
+/// the IIDX ID if there is no user to press the buttons
+fn ambiguous_reply(user: Option<&User>, player: &Player, profiles: &[Profile]) -> Output {
+    let Some(user) = user else {
+        return (ambiguous_player(&player.param, profiles), None);
+    };
+    let keyboard = profiles.iter().map(|p| {
+        [InlineKeyboardButton::callback(
+            candidate(p),
+            Callback::PickProfile {
+                user: user.id,
+                version: player.version,
+                iidx_id: p.iidx_id.clone(),
+            }
+            .to_string(),
+        )]
+    });
+    (
+        escape(&format!(
+            "Several profiles match {}, pick one:",
+            player.param
+        )),
+        Some(InlineKeyboardMarkup::new(keyboard)),
+    )
+}
+
+/// Reply to the command with the output and its keyboard, or the error
+async fn send_output(
+    bot: Bot,
+    message: &Message,
+    output: Result<Output>,
+) -> std::result::Result<(), Box<dyn Error + Send + Sync>> {
+    let (output, keyboard) = output.unwrap_or_else(|e| (error_reply(&e), None));
+    let mut request = bot
+        .send_message(message.chat.id, output)
+        .parse_mode(ParseMode::MarkdownV2)
+        .reply_parameters(ReplyParameters::new(message.id));
+    if let Some(keyboard) = keyboard {
+        request = request.reply_markup(keyboard);
+    }
+    request.await?;
+
+    Ok(())
+}
 
-    Ok(if !dj_name_profiles.is_empty() {
-        dj_name_profiles
+async fn get_profiles(client: &ArcanaClient, version: u32, param: &str) -> Result<Vec<Profile>> {
+    find_profiles(client, version, param).await
+}
+
+/// Get the linked profile in the version, which is looked up by the IIDX ID
+/// if it is not the linked version
+async fn get_linked_profile(
+    client: &ArcanaClient,
+    link: &Link,
+    version: u32,
+) -> Result<Option<Profile>> {
+    if version == link.version {
+        get_profile_by_id(client, version, &link.profile_id).await
     } else {
-        get_profile_using_id(version, param).await?
+        Ok(get_profile_using_id(client, version, &link.iidx_id)
+            .await?
+            .pop())
+    }
+}
+
+/// Resolve the player given in a command, or the profile linked to the user
+async fn resolve_profile(
+    ctx: &ArcanaContext,
+    user: Option<&User>,
+    player: Option<Player>,
+) -> Result<Resolved> {
+    let (version, profile) = match player {
+        Some(player) => {
+            let mut profiles = get_profiles(&ctx.client, player.version, &player.param).await?;
+            if profiles.len() > 1 {
+                return Ok(Resolved::Reply(ambiguous_reply(user, &player, &profiles)));
+            }
+            (player.version, profiles.pop())
+        }
+        None => match user {
+            Some(user) => match ctx.links.get(user.id).await {
+                Some(link) => (
+                    link.version,
+                    get_linked_profile(&ctx.client, &link, link.version).await?,
+                ),
+                None => {
+                    return Ok(Resolved::Reply((
+                        escape(
+                            "Please give VERSION DJ_NAME/IIDX_ID, or link your profile with /iidxlink DJ_NAME/IIDX_ID first.",
+                        ),
+                        None,
+                    )))
+                }
+            },
+            None => return Ok(Resolved::Reply(("Not found".to_owned(), None))),
+        },
+    };
+
+    Ok(match profile {
+        Some(profile) => Resolved::Profile(version, profile),
+        None => Resolved::Reply(("Not found".to_owned(), None)),
     })
 }
 
+/// Run the command the profile was picked for again with the picked profile,
+/// replying to the original command
+pub async fn profile_picked(
+    bot: Bot,
+    message: &MaybeInaccessibleMessage,
+    ctx: &ArcanaContext,
+    bpi_table: &BpiTable,
+    version: u32,
+    iidx_id: &str,
+) -> std::result::Result<(), Box<dyn Error + Send + Sync>> {
+    let Some(command) = message
+        .regular_message()
+        .and_then(Message::reply_to_message)
+    else {
+        return Ok(());
+    };
+    bot.edit_message_text(
+        message.chat().id,
+        message.id(),
+        escape(&format!("Picked {}", iidx_id)),
+    )
+    .parse_mode(ParseMode::MarkdownV2)
+    .await?;
+
+    let me = bot.get_me().await?;
+    let Some(Ok(parsed)) = command
+        .text()
+        .map(|text| Command::parse(text, me.username()))
+    else {
+        return Ok(());
+    };
+    let player = Some(Player {
+        version,
+        param: iidx_id.to_owned(),
+    });
+    let command = command.clone();
+    match parsed {
+        Command::IIDXProfile { .. } => profile(bot, command, ctx, player).await?,
+        Command::IIDXProgress { .. } => progress(bot, command, ctx, player).await?,
+        Command::IIDXRecent { options, .. } => recent(bot, command, ctx, player, options).await?,
+        Command::IIDXBest { title, .. } => best(bot, command, ctx, player, &title).await?,
+        Command::IIDXLamps {
+            level, play_style, ..
+        } => lamps(bot, command, ctx, player, level, play_style).await?,
+        Command::BpiTotal { .. } => bpi_total(bot, command, ctx, bpi_table, player).await?,
+        _ => log::warn!("No profile to pick for {:?}", command.text()),
+    }
+
+    Ok(())
+}
+
+pub mod best;
+pub mod board;
+pub mod bpi;
+pub mod cache;
+pub mod lamps;
+pub mod link;
 pub mod music;
+pub mod notify;
 pub mod profile;
+pub mod progress;
 pub mod recent;
+pub mod vs;
 
+pub use best::best;
+pub use board::board;
+pub use bpi::bpi_total;
+pub use cache::cache;
+pub use lamps::lamps;
+pub use link::link;
 pub use music::music;
+pub use notify::notify;
 pub use profile::profile;
+pub use progress::progress;
 pub use recent::recent;
+pub use vs::vs;
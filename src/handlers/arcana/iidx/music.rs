@@ -1,34 +1,285 @@
-use std::error::Error;
-use teloxide::{prelude::*, types::ParseMode, types::ReplyParameters, utils::markdown::*};
+use std::{collections::HashMap, error::Error};
+use teloxide::{
+    prelude::*,
+    types::{
+        InlineKeyboardButton, InlineKeyboardMarkup, MaybeInaccessibleMessage, ParseMode,
+        ReplyParameters, User,
+    },
+    utils::markdown::*,
+    ApiError, RequestError,
+};
 
-use crate::arcana::iidx::{get_charts, get_music_folder};
+use crate::{
+    arcana::{
+        iidx::{get_best, Best, Chart, Music},
+        PlayStyle, Result,
+    },
+    handlers::{
+        arcana::{error_reply, search_catalogue, ArcanaContext},
+        callback::Callback,
+    },
+    DEFAULT_IIDX_VERSION,
+};
+
+use super::get_linked_profile;
+
+/// Search the catalogue of the version by title, artist or genre
+pub(super) async fn search(ctx: &ArcanaContext, version: u32, query: &str) -> Result<Vec<Music>> {
+    let catalogue = ctx.cache.catalogue(&ctx.client, version).await?;
+    Ok(
+        search_catalogue(&catalogue, query, |m| [&m.title, &m.artist, &m.genre])
+            .into_iter()
+            .cloned()
+            .collect(),
+    )
+}
+
+/// Render the song with a table of its charts in the play style, along
+/// with the personal bests of the named player if given
+fn render_music(
+    m: &Music,
+    charts: &[Chart],
+    play_style: PlayStyle,
+    bests: Option<(&str, &HashMap<String, Best>)>,
+) -> String {
+    let mut charts = charts
+        .iter()
+        .filter(|c| c.play_style == play_style)
+        .collect::<Vec<_>>();
+    charts.sort_by_key(|c| c.difficulty);
+
+    let mut table = format!("{:<11} {:>2} {:>5}", play_style, "LV", "NOTES");
+    if bests.is_some() {
+        table.push_str(&format!(" {:<4} {:>4}", "LAMP", "EX"));
+    }
+    table.push_str(" BPM");
+    for c in charts.iter() {
+        table.push_str(&format!(
+            "\n{:<11} {:>2} {:>5}",
+            c.difficulty.to_string(),
+            c.rating,
+            c.notes
+        ));
+        if let Some((_, bests)) = bests {
+            match bests.get(&c.id) {
+                Some(best) => table.push_str(&format!(
+                    " {:<4} {:>4}",
+                    best.lamp.value.short(),
+                    best.ex_score.value
+                )),
+                None => table.push_str(&format!(" {:<4} {:>4}", "-", "-")),
+            }
+        }
+        if c.bpm_min == c.bpm_max {
+            table.push_str(&format!(" {}", c.bpm_max));
+        } else {
+            table.push_str(&format!(" {}-{}", c.bpm_min, c.bpm_max));
+        }
+    }
+    if charts.is_empty() {
+        table.push_str(&format!("\nNo {} charts", play_style));
+    }
+
+    format!(
+        "{}\n{}\n{}\n{}{}",
+        escape(&m.genre),
+        bold(&escape(&m.title)),
+        escape(&m.artist),
+        // the bests shown depend on who asked, so say whose they are
+        bests.map_or(String::new(), |(dj_name, _)| {
+            escape(&format!("Personal bests of {}\n", dj_name))
+        }),
+        code_block(&table)
+    )
+}
+
+/// Buttons to switch between the play styles of a song
+fn play_style_keyboard(version: u32, music_id: &str) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new([[PlayStyle::Single, PlayStyle::Double].map(|play_style| {
+        InlineKeyboardButton::callback(
+            play_style.to_string(),
+            Callback::Music {
+                version,
+                music_id: music_id.to_owned(),
+                play_style,
+            }
+            .to_string(),
+        )
+    })])
+}
+
+async fn music_output(
+    ctx: &ArcanaContext,
+    user: Option<&User>,
+    version: u32,
+    music_id: &str,
+    play_style: PlayStyle,
+) -> Result<Option<String>> {
+    let Some(m) = ctx.cache.music(&ctx.client, version, music_id).await? else {
+        return Ok(None);
+    };
+    let charts = ctx.cache.charts(&ctx.client, version, &m.id).await?;
+
+    // personal bests of the user if linked
+    let link = match user {
+        Some(user) => ctx.links.get(user.id).await,
+        None => None,
+    };
+    let bests = match &link {
+        Some(link) => match get_linked_profile(&ctx.client, link, version).await? {
+            Some(p) => Some((
+                p.dj_name,
+                get_best(&ctx.client, version, &p.id, &m.id).await?,
+            )),
+            None => None,
+        },
+        None => None,
+    };
+
+    Ok(Some(render_music(
+        &m,
+        &charts,
+        play_style,
+        bests
+            .as_ref()
+            .map(|(dj_name, bests)| (dj_name.as_str(), bests)),
+    )))
+}
 
 pub async fn music(
     bot: Bot,
     message: Message,
-    version: u32,
+    ctx: &ArcanaContext,
+    version: Option<u32>,
     title: &str,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    let music = get_music_folder(version, version).await?;
-    let mut output = "Not found".to_owned();
-    for m in music {
-        if m.title == title {
-            let charts = get_charts(version, &m.id).await?;
-            output = format!(
-                "{}\n{}\n{}\n\nDifficulties:\n",
-                escape(&m.genre),
-                escape(&m.title),
-                escape(&m.artist)
-            );
-            for c in charts {
-                output.push_str(&format!("{} {} {}\n", c.play_style, c.difficulty, c.rating));
-            }
+) -> std::result::Result<(), Box<dyn Error + Send + Sync>> {
+    let user = message.from.as_ref();
+    let version = match version {
+        Some(version) => version,
+        None => match user {
+            Some(user) => ctx
+                .links
+                .get(user.id)
+                .await
+                .map_or(*DEFAULT_IIDX_VERSION, |link| link.version),
+            None => *DEFAULT_IIDX_VERSION,
+        },
+    };
+    let request = match search(ctx, version, title).await.as_deref() {
+        Err(e) => bot.send_message(message.chat.id, error_reply(e)),
+        Ok([]) => bot.send_message(message.chat.id, "Not found"),
+        Ok([m]) => match music_output(ctx, user, version, &m.id, PlayStyle::Single).await {
+            Ok(Some(output)) => bot
+                .send_message(message.chat.id, output)
+                .reply_markup(play_style_keyboard(version, &m.id)),
+            Ok(None) => bot.send_message(message.chat.id, "Not found"),
+            Err(e) => bot.send_message(message.chat.id, error_reply(&e)),
+        },
+        Ok(candidates) => {
+            let keyboard = candidates.iter().map(|m| {
+                vec![InlineKeyboardButton::callback(
+                    format!("{} / {}", m.title, m.artist),
+                    Callback::Music {
+                        version,
+                        music_id: m.id.clone(),
+                        play_style: PlayStyle::Single,
+                    }
+                    .to_string(),
+                )]
+            });
+            bot.send_message(message.chat.id, escape("Multiple songs found, pick one:"))
+                .reply_markup(InlineKeyboardMarkup::new(keyboard))
         }
-    }
-    bot.send_message(message.chat.id, output)
+    };
+    request
         .parse_mode(ParseMode::MarkdownV2)
         .reply_parameters(ReplyParameters::new(message.id))
         .await?;
 
     Ok(())
 }
+
+/// Show the charts of a song picked from the candidates or switched to
+/// another play style, in place of the message with the buttons
+pub async fn music_picked(
+    bot: Bot,
+    message: &MaybeInaccessibleMessage,
+    ctx: &ArcanaContext,
+    user: &User,
+    version: u32,
+    music_id: &str,
+    play_style: PlayStyle,
+) -> std::result::Result<(), Box<dyn Error + Send + Sync>> {
+    let (chat_id, message_id) = (message.chat().id, message.id());
+    let request = match music_output(ctx, Some(user), version, music_id, play_style).await {
+        Ok(Some(output)) => bot
+            .edit_message_text(chat_id, message_id, output)
+            .reply_markup(play_style_keyboard(version, music_id)),
+        Ok(None) => bot.edit_message_text(chat_id, message_id, "Not found"),
+        Err(e) => bot.edit_message_text(chat_id, message_id, error_reply(&e)),
+    };
+    match request.parse_mode(ParseMode::MarkdownV2).await {
+        // pressing the play style already shown leaves the message as is
+        Ok(_) | Err(RequestError::Api(ApiError::MessageNotModified)) => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arcana::iidx::{Difficulty, Lamp, Record};
+
+    #[test]
+    fn test_render_music() {
+        let m = Music {
+            id: "G6vGmV2XC2Y".to_owned(),
+            artist: "Ryu*".to_owned(),
+            folder: 28,
+            genre: "HI-TECH".to_owned(),
+            title: "Mind Mapping".to_owned(),
+        };
+        let chart = |difficulty, play_style, bpm_min| Chart {
+            difficulty,
+            play_style,
+            bpm_min,
+            bpm_max: 180.0,
+            notes: 1621,
+            rating: 12,
+            ..Default::default()
+        };
+        let charts = [
+            chart(Difficulty::Another, PlayStyle::Single, 90.0),
+            chart(Difficulty::Hyper, PlayStyle::Single, 180.0),
+            chart(Difficulty::Another, PlayStyle::Double, 180.0),
+        ];
+
+        let output = render_music(&m, &charts, PlayStyle::Single, None);
+        assert!(output.contains("HYPER       12  1621 180\nANOTHER     12  1621 90-180"));
+        assert!(render_music(&m, &charts[..2], PlayStyle::Double, None).contains("No DP charts"));
+
+        let bests = HashMap::from([(
+            String::new(),
+            Best {
+                chart_id: String::new(),
+                ex_score: Record {
+                    value: 2741,
+                    timestamp: "2021-10-03T13:01:52Z".to_owned(),
+                },
+                lamp: Record {
+                    value: Lamp::HardClear,
+                    timestamp: "2021-10-03T13:01:52Z".to_owned(),
+                },
+                miss_count: None,
+            },
+        )]);
+        let output = render_music(
+            &m,
+            &charts[1..2],
+            PlayStyle::Single,
+            Some(("ORIGIN", &bests)),
+        );
+        assert!(output.contains("Personal bests of ORIGIN\n```"));
+        assert!(output.contains("LV NOTES LAMP   EX BPM\nHYPER       12  1621 HC   2741 180"));
+    }
+}
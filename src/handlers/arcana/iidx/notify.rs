@@ -0,0 +1,46 @@
+use std::error::Error;
+use teloxide::{prelude::*, types::ReplyParameters};
+
+use crate::links::Links;
+
+/// Opt in or out of personal best notifications in the chat
+pub async fn notify(
+    bot: Bot,
+    message: Message,
+    links: &Links,
+    action: &str,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let Some(user) = message.from.as_ref() else {
+        return Ok(());
+    };
+
+    let output = match action.trim() {
+        "on" => {
+            if links.subscribe(user.id, message.chat.id).await {
+                "New personal bests will be posted in this chat!"
+            } else {
+                "Please link your profile with /iidxlink DJ_NAME/IIDX_ID first."
+            }
+        }
+        "off" => {
+            if links.unsubscribe(user.id).await {
+                "Personal best notifications are turned off."
+            } else {
+                "Personal best notifications are not turned on."
+            }
+        }
+        "" => match links.get(user.id).await.and_then(|link| link.notify) {
+            Some(s) if s.chat_id == message.chat.id => {
+                "Personal best notifications are on in this chat."
+            }
+            Some(_) => "Personal best notifications are on in another chat.",
+            None => "Personal best notifications are off. Usage: /iidxnotify on/off",
+        },
+        _ => "Usage: /iidxnotify on/off",
+    };
+    bot.send_message(message.chat.id, output)
+        .reply_parameters(ReplyParameters::new(message.id))
+        .await?;
+
+    Ok(())
+}
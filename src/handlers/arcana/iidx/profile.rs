@@ -1,50 +1,94 @@
 use std::error::Error;
-use teloxide::{prelude::*, types::ParseMode, types::ReplyParameters, utils::markdown::*};
+use teloxide::{prelude::*, utils::markdown::*};
 
-use super::get_profiles;
+use super::{resolve_profile, send_output, Output, Player, Resolved};
+use crate::{
+    arcana::{
+        iidx::{Profile, Score},
+        Result,
+    },
+    handlers::arcana::ArcanaContext,
+};
+
+/// Render the profile with the stats of both play styles
+fn render_profile(profile: &Profile) -> String {
+    let rank = |score: &Score| {
+        escape(
+            &score
+                .rank
+                .as_ref()
+                .map_or("-".to_owned(), |r| r.to_string()),
+        )
+    };
+    let sp = &profile.sp;
+    let dp = &profile.dp;
+    format!(
+        "DJ NAME: {}\nIIDX ID: {}\n\n{}\nDJ POINTS: {}\nPLAYS: {}\n\
+        RANKS: {}\n\n{}\nDJ POINTS: {}\nPLAYS: {}\n\
+        RANKS: {}",
+        escape(&profile.dj_name),
+        escape(&profile.iidx_id),
+        bold("SP"),
+        sp.dj_points,
+        sp.plays,
+        rank(sp),
+        bold("DP"),
+        dp.dj_points,
+        dp.plays,
+        rank(dp)
+    )
+}
+
+async fn profile_output(
+    ctx: &ArcanaContext,
+    message: &Message,
+    player: Option<Player>,
+) -> Result<Output> {
+    let profile = match resolve_profile(ctx, message.from.as_ref(), player).await? {
+        Resolved::Profile(_, profile) => profile,
+        Resolved::Reply(reply) => return Ok(reply),
+    };
+
+    Ok((render_profile(&profile), None))
+}
 
 pub async fn profile(
     bot: Bot,
     message: Message,
-    version: u32,
-    param: &str,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    let profiles = get_profiles(version, param).await?;
-    let output = profiles
-        .iter()
-        .map(|profile| {
-            let sp = &profile.sp;
-            let dp = &profile.dp;
-            format!(
-                "DJ NAME: {}\nIIDX ID: {}\n\n{}\nDJ POINTS: {}\nPLAYS: {}\n\
-                RANKS: {}\n\n{}\nDJ POINTS: {}\nPLAYS: {}\n\
-                RANKS: {}",
-                escape(&profile.dj_name),
-                escape(&profile.iidx_id),
-                bold("SP"),
-                sp.dj_points,
-                sp.plays,
-                if let Some(ranks) = &sp.rank {
-                    ranks
-                } else {
-                    "NULL"
-                },
-                bold("DP"),
-                dp.dj_points,
-                dp.plays,
-                if let Some(ranks) = &dp.rank {
-                    ranks
-                } else {
-                    "NULL"
-                }
-            )
-        })
-        .collect::<Vec<String>>()
-        .join("\n------\n");
-    bot.send_message(message.chat.id, output)
-        .parse_mode(ParseMode::MarkdownV2)
-        .reply_parameters(ReplyParameters::new(message.id))
-        .await?;
-
-    Ok(())
+    ctx: &ArcanaContext,
+    player: Option<Player>,
+) -> std::result::Result<(), Box<dyn Error + Send + Sync>> {
+    let output = profile_output(ctx, &message, player).await;
+    send_output(bot, &message, output).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arcana::iidx::DanRank;
+
+    #[test]
+    fn test_render_profile() {
+        let profile = Profile {
+            id: "C3PttzgAx6F".to_owned(),
+            dj_name: "ORIGIN".to_owned(),
+            iidx_id: "1015-0869".to_owned(),
+            sp: Score {
+                dj_points: 1532,
+                plays: 842,
+                rank: Some(DanRank::Dan7),
+            },
+            dp: Score {
+                dj_points: 0,
+                plays: 3,
+                rank: None,
+            },
+        };
+
+        assert_eq!(
+            render_profile(&profile),
+            "DJ NAME: ORIGIN\nIIDX ID: 1015\\-0869\n\n*SP*\nDJ POINTS: 1532\nPLAYS: 842\n\
+            RANKS: 七段 \\(7th Dan\\)\n\n*DP*\nDJ POINTS: 0\nPLAYS: 3\nRANKS: \\-"
+        );
+    }
 }
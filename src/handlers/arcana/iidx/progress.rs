@@ -0,0 +1,170 @@
+use chrono::Utc;
+use std::error::Error;
+use teloxide::{prelude::*, utils::markdown::*};
+
+use super::{resolve_profile, send_output, Output, Player, Resolved};
+use crate::{
+    arcana::{
+        iidx::{Profile, Score},
+        Result,
+    },
+    handlers::arcana::ArcanaContext,
+    progress::{as_of, days_before, rank_changes, Snapshot},
+};
+
+/// Periods to compare the current stats with, in days
+const PERIODS: [(i64, &str); 3] = [(7, "1 week"), (30, "1 month"), (90, "3 months")];
+
+/// Render the changes of DJ POINTS and plays of a play style over the
+/// periods, and when the dan rank changed
+fn render_play_style(
+    name: &str,
+    now: &Score,
+    history: &[Snapshot],
+    today: &str,
+    version: u32,
+    score: impl Fn(&Snapshot) -> &Score,
+) -> String {
+    let mut table = format!(
+        "{:<16} {:>9} {:>6}\n{:<16} {:>9} {:>6}",
+        name, "DJ POINTS", "PLAYS", "Now", now.dj_points, now.plays
+    );
+    let mut rows = PERIODS
+        .iter()
+        .filter_map(|(days, label)| {
+            let then = as_of(history, &days_before(today, *days)?)?;
+            Some((format!("{} ago", label), then))
+        })
+        .collect::<Vec<_>>();
+    if let Some(first) = history.first() {
+        rows.push((format!("Since {}", first.date), first));
+    }
+    for (label, then) in rows {
+        // DJ POINTS and plays start over on a new version
+        if then.version != version {
+            continue;
+        }
+        table.push_str(&format!(
+            "\n{:<16} {:>+9} {:>+6}",
+            label,
+            i64::from(now.dj_points) - i64::from(score(then).dj_points),
+            i64::from(now.plays) - i64::from(score(then).plays)
+        ));
+    }
+
+    let changes = rank_changes(history, &score)
+        .into_iter()
+        .map(|(date, from, to)| {
+            format!(
+                "{}: {} → {} {}",
+                date,
+                from.as_ref().map_or("-", |r| r.japanese()),
+                to.as_ref().map_or("-", |r| r.japanese()),
+                // no rank is the lowest, e.g. after a new version starts
+                if to > from { "↑" } else { "↓" }
+            )
+        })
+        .collect::<Vec<String>>();
+    format!(
+        "{}{}",
+        code_block(&table),
+        if changes.is_empty() {
+            String::new()
+        } else {
+            escape(&format!("\n{} dan:\n{}", name, changes.join("\n")))
+        }
+    )
+}
+
+fn render_progress(version: u32, p: &Profile, history: &[Snapshot], today: &str) -> String {
+    format!(
+        "{}\n{}\n{}\n{}",
+        bold(&escape(&format!("{} ({})", p.dj_name, p.iidx_id))),
+        escape(&format!(
+            "Dan: SP {} / DP {}",
+            p.sp.rank.as_ref().map_or("-".to_owned(), |r| r.to_string()),
+            p.dp.rank.as_ref().map_or("-".to_owned(), |r| r.to_string())
+        )),
+        render_play_style("SP", &p.sp, history, today, version, |s| &s.sp),
+        render_play_style("DP", &p.dp, history, today, version, |s| &s.dp),
+    )
+}
+
+async fn progress_output(
+    ctx: &ArcanaContext,
+    message: &Message,
+    player: Option<Player>,
+) -> Result<Output> {
+    let (version, p) = match resolve_profile(ctx, message.from.as_ref(), player).await? {
+        Resolved::Profile(version, p) => (version, p),
+        Resolved::Reply(reply) => return Ok(reply),
+    };
+    let Some(history) = ctx.links.history(&p.iidx_id).await else {
+        return Ok((
+            escape(
+                "Progress is only tracked for linked profiles, link one with /iidxlink DJ_NAME/IIDX_ID.",
+            ),
+            None,
+        ));
+    };
+
+    Ok((
+        render_progress(
+            version,
+            &p,
+            &history,
+            &Utc::now().format("%Y-%m-%d").to_string(),
+        ),
+        None,
+    ))
+}
+
+/// Show how the DJ POINTS, plays and dan ranks of a linked profile changed
+pub async fn progress(
+    bot: Bot,
+    message: Message,
+    ctx: &ArcanaContext,
+    player: Option<Player>,
+) -> std::result::Result<(), Box<dyn Error + Send + Sync>> {
+    let output = progress_output(ctx, &message, player).await;
+    send_output(bot, &message, output).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arcana::iidx::DanRank;
+
+    #[test]
+    fn test_render_progress() {
+        let score = |dj_points, plays, rank| Score {
+            dj_points,
+            plays,
+            rank,
+        };
+        let snapshot = |date: &str, sp| Snapshot {
+            date: date.to_owned(),
+            version: 28,
+            sp,
+            dp: score(0, 3, None),
+        };
+        let history = [
+            snapshot("2021-08-01", score(1200, 600, Some(DanRank::Dan6))),
+            snapshot("2021-09-20", score(1500, 820, Some(DanRank::Dan7))),
+        ];
+        let profile = Profile {
+            id: "C3PttzgAx6F".to_owned(),
+            dj_name: "ORIGIN".to_owned(),
+            iidx_id: "1015-0869".to_owned(),
+            sp: score(1532, 842, Some(DanRank::Dan7)),
+            dp: score(0, 3, None),
+        };
+
+        let output = render_progress(28, &profile, &history, "2021-10-03");
+        assert!(output
+            .contains("Now                   1532    842\n1 week ago             +32    +22\n"));
+        assert!(output.contains("Since 2021-08-01      +332   +242\n```"));
+        assert!(!output.contains("3 months ago"));
+        assert!(output.contains("SP dan:\n2021\\-09\\-20: 六段 → 七段 ↑"));
+    }
+}
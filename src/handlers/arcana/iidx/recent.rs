@@ -1,43 +1,369 @@
-use std::error::Error;
-use teloxide::{prelude::*, types::ParseMode, types::ReplyParameters, utils::markdown::*};
+use serde_json::json;
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt,
+    str::FromStr,
+};
+use teloxide::{
+    prelude::*,
+    types::{InlineKeyboardButton, InlineKeyboardMarkup, MaybeInaccessibleMessage, ParseMode},
+    utils::markdown::*,
+};
 
-use super::get_profiles;
-use crate::arcana::iidx::{get_charts, get_most_recent, get_music, Chart};
+use super::{resolve_profile, send_output, Output, Player, Resolved};
+use crate::{
+    arcana::{
+        get_by_ids,
+        iidx::{get_recent, plays, Chart, Difficulty, Lamp, Music},
+        Game, PlayStyle, Result,
+    },
+    handlers::{
+        arcana::{error_reply, ArcanaContext},
+        callback::Callback,
+    },
+};
 
-pub async fn recent(
-    bot: Bot,
-    message: Message,
-    version: u32,
-    param: &str,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    let mut profiles = get_profiles(version, param).await?;
-    let mut output = "Not found".to_owned();
-    if let Some(p) = profiles.pop() {
-        if let Some(r) = get_most_recent(version, &p.id).await? {
-            let music = get_music(28, &r.music_id).await?.unwrap();
-            let mut chart = Chart::default();
-            for c in get_charts(28, &r.music_id).await? {
-                if c.id == r.chart_id {
-                    chart = c;
+/// Plays shown on a page
+const PAGE_SIZE: usize = 10;
+/// Maximum number of plays to list
+const MAX_PLAYS: u32 = 50;
+/// Plays to look through when filtering by chart
+const SCAN_LIMIT: u32 = 200;
+
+/// Number of plays to list and filters of `/iidxrecent`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecentOptions {
+    pub n: u32,
+    pub play_style: Option<PlayStyle>,
+    pub difficulty: Option<Difficulty>,
+    pub level: Option<u32>,
+    pub lamp: Option<Lamp>,
+}
+
+impl Default for RecentOptions {
+    fn default() -> Self {
+        Self {
+            n: 10,
+            play_style: None,
+            difficulty: None,
+            level: None,
+            lamp: None,
+        }
+    }
+}
+
+impl RecentOptions {
+    /// Whether plays are filtered by their charts
+    fn filters_charts(&self) -> bool {
+        self.play_style.is_some() || self.difficulty.is_some() || self.level.is_some()
+    }
+
+    fn matches(&self, chart: &Chart) -> bool {
+        self.play_style.is_none_or(|p| p == chart.play_style)
+            && self.difficulty.is_none_or(|d| d == chart.difficulty)
+            && self.level.is_none_or(|l| l == chart.rating)
+    }
+}
+
+impl fmt::Display for RecentOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.n)?;
+        if let Some(play_style) = self.play_style {
+            write!(f, ",{}", play_style)?;
+        }
+        if let Some(difficulty) = self.difficulty {
+            write!(f, ",{}", difficulty.short())?;
+        }
+        if let Some(level) = self.level {
+            write!(f, ",LV{}", level)?;
+        }
+        if let Some(lamp) = self.lamp {
+            write!(f, ",{}", lamp.short())?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for RecentOptions {
+    type Err = String;
+
+    /// Parse options like `20 SP A LV12 HC` in any order, where the bare
+    /// number is the number of plays
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut options = RecentOptions::default();
+        for token in s.split([' ', ',']).filter(|t| !t.is_empty()) {
+            let upper = token.to_uppercase();
+            if let Ok(n) = token.parse() {
+                if n == 0 || n > MAX_PLAYS {
+                    return Err(format!("N should be between 1 and {}", MAX_PLAYS));
                 }
+                options.n = n;
+            } else if let Some(level) = upper.strip_prefix("LV") {
+                options.level = Some(
+                    level
+                        .parse()
+                        .map_err(|_| format!("invalid level: {}", token))?,
+                );
+            } else if let Ok(play_style) = token.parse() {
+                options.play_style = Some(play_style);
+            } else if let Ok(difficulty) = token.parse() {
+                options.difficulty = Some(difficulty);
+            } else if let Ok(lamp) = token.parse() {
+                options.lamp = Some(lamp);
+            } else {
+                return Err(format!("invalid filter: {}", token));
             }
-            output = format!(
-                "{}\n{} {} {}\n\nLamp: {}\nEX Score: {}\nMiss Count: {}\n\nTimestamp: {}",
-                escape(&music.title),
+        }
+        Ok(options)
+    }
+}
+
+/// A page of the recent plays of a profile
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecentPage {
+    pub version: u32,
+    pub profile_id: String,
+    pub page: usize,
+    pub options: RecentOptions,
+}
+
+impl fmt::Display for RecentPage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}:{}",
+            self.version, self.profile_id, self.page, self.options
+        )
+    }
+}
+
+impl FromStr for RecentPage {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut parts = s.splitn(4, ':');
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(version), Some(profile_id), Some(page), Some(options)) => Ok(RecentPage {
+                version: version
+                    .parse()
+                    .map_err(|_| format!("invalid version: {}", version))?,
+                profile_id: profile_id.to_owned(),
+                page: page
+                    .parse()
+                    .map_err(|_| format!("invalid page: {}", page))?,
+                options: options.parse()?,
+            }),
+            _ => Err(format!("invalid page: {}", s)),
+        }
+    }
+}
+
+async fn page_output(ctx: &ArcanaContext, page: &RecentPage) -> Result<Output> {
+    let RecentPage {
+        version,
+        profile_id,
+        options,
+        ..
+    } = page;
+    let mut query = plays(profile_id);
+    if let Some(lamp) = options.lamp {
+        query = query.eq("lamp", json!(lamp));
+    }
+    let scan = if options.filters_charts() {
+        SCAN_LIMIT
+    } else {
+        options.n
+    };
+
+    let plays = get_recent(&ctx.client, *version, query, scan).await?;
+    // music and charts of all the plays are fetched at once
+    let music_ids = plays
+        .iter()
+        .map(|r| r.music_id.as_str())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    let chart_ids = plays
+        .iter()
+        .map(|r| r.chart_id.as_str())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    let (music, charts): (HashMap<String, Music>, HashMap<String, Chart>) = if plays.is_empty() {
+        (HashMap::new(), HashMap::new())
+    } else {
+        (
+            get_by_ids::<Music>(&ctx.client, Game::Iidx, *version, "music/", &music_ids)
+                .await?
+                .into_iter()
+                .map(|m| (m.id.clone(), m))
+                .collect(),
+            get_by_ids::<Chart>(&ctx.client, Game::Iidx, *version, "charts/", &chart_ids)
+                .await?
+                .into_iter()
+                .map(|c| (c.id.clone(), c))
+                .collect(),
+        )
+    };
+
+    let mut entries = Vec::new();
+    for r in plays.iter() {
+        if entries.len() == options.n as usize {
+            break;
+        }
+        let chart = charts.get(&r.chart_id).cloned().unwrap_or_default();
+        if !options.matches(&chart) {
+            continue;
+        }
+        // songs missing from Arcana are shown by their ID
+        let title = music.get(&r.music_id).map_or(&r.music_id, |m| &m.title);
+        entries.push(format!(
+            "{}\n{}",
+            bold(&escape(title)),
+            escape(&format!(
+                "{} {} {} | {} | EX {} | MISS {}\n{}",
                 chart.play_style,
                 chart.difficulty,
                 chart.rating,
                 r.lamp,
                 r.ex_score,
-                r.miss_count.unwrap_or_default(),
-                escape(&r.timestamp),
-            )
-        }
+                r.miss_count
+                    .map(|m| m.to_string())
+                    .unwrap_or("-".to_owned()),
+                r.timestamp,
+            ))
+        ));
+    }
+    if entries.is_empty() {
+        return Ok(("Not found".to_owned(), None));
+    }
+
+    let pages = entries.len().div_ceil(PAGE_SIZE);
+    let current = page.page.clamp(1, pages);
+    let output = format!(
+        "{}\n\n{}",
+        escape(&format!("Recent plays ({}/{})", current, pages)),
+        entries[(current - 1) * PAGE_SIZE..]
+            .iter()
+            .take(PAGE_SIZE)
+            .cloned()
+            .collect::<Vec<String>>()
+            .join("\n\n")
+    );
+
+    let button = |text: &str, page_number: usize| {
+        InlineKeyboardButton::callback(
+            text,
+            Callback::Recent(RecentPage {
+                page: page_number,
+                ..page.clone()
+            })
+            .to_string(),
+        )
+    };
+    let mut buttons = Vec::new();
+    if current > 1 {
+        buttons.push(button("« Prev", current - 1));
+    }
+    if current < pages {
+        buttons.push(button("Next »", current + 1));
+    }
+    let keyboard = (!buttons.is_empty()).then(|| InlineKeyboardMarkup::new([buttons]));
+
+    Ok((output, keyboard))
+}
+
+async fn recent_output(
+    ctx: &ArcanaContext,
+    message: &Message,
+    player: Option<Player>,
+    options: RecentOptions,
+) -> Result<Output> {
+    let (version, p) = match resolve_profile(ctx, message.from.as_ref(), player).await? {
+        Resolved::Profile(version, p) => (version, p),
+        Resolved::Reply(reply) => return Ok(reply),
+    };
+    page_output(
+        ctx,
+        &RecentPage {
+            version,
+            profile_id: p.id,
+            page: 1,
+            options,
+        },
+    )
+    .await
+}
+
+pub async fn recent(
+    bot: Bot,
+    message: Message,
+    ctx: &ArcanaContext,
+    player: Option<Player>,
+    options: RecentOptions,
+) -> std::result::Result<(), Box<dyn Error + Send + Sync>> {
+    let output = recent_output(ctx, &message, player, options).await;
+    send_output(bot, &message, output).await
+}
+
+/// Turn to another page of the recent plays
+pub async fn recent_page(
+    bot: Bot,
+    message: &MaybeInaccessibleMessage,
+    ctx: &ArcanaContext,
+    page: &RecentPage,
+) -> std::result::Result<(), Box<dyn Error + Send + Sync>> {
+    let (output, keyboard) = page_output(ctx, page)
+        .await
+        .unwrap_or_else(|e| (error_reply(&e), None));
+    let mut request = bot
+        .edit_message_text(message.chat().id, message.id(), output)
+        .parse_mode(ParseMode::MarkdownV2);
+    if let Some(keyboard) = keyboard {
+        request = request.reply_markup(keyboard);
     }
-    bot.send_message(message.chat.id, output)
-        .parse_mode(ParseMode::MarkdownV2)
-        .reply_parameters(ReplyParameters::new(message.id))
-        .await?;
+    request.await?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recent_options() {
+        let options = "20 sp A lv12 HC".parse::<RecentOptions>().unwrap();
+        assert_eq!(
+            options,
+            RecentOptions {
+                n: 20,
+                play_style: Some(PlayStyle::Single),
+                difficulty: Some(Difficulty::Another),
+                level: Some(12),
+                lamp: Some(Lamp::HardClear),
+            }
+        );
+        assert_eq!(options.to_string(), "20,SP,A,LV12,HC");
+        assert_eq!(options.to_string().parse(), Ok(options));
+
+        assert_eq!("".parse(), Ok(RecentOptions::default()));
+        assert!("100".parse::<RecentOptions>().is_err());
+        assert!("SP XYZ".parse::<RecentOptions>().is_err());
+    }
+
+    #[test]
+    fn test_recent_page() {
+        let page = RecentPage {
+            version: 28,
+            profile_id: "C3PttzgAx6F".to_owned(),
+            page: 2,
+            options: "50 DP L LV12 EXHC".parse().unwrap(),
+        };
+        let data = Callback::Recent(page.clone()).to_string();
+        // Telegram limits callback data to 64 bytes
+        assert!(data.len() <= 64);
+        assert_eq!(data.parse(), Ok(Callback::Recent(page)));
+    }
+}
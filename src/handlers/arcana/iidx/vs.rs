@@ -0,0 +1,346 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{HashMap, HashSet},
+    error::Error,
+};
+use teloxide::{prelude::*, types::ParseMode, types::ReplyParameters, utils::markdown::*};
+
+use super::{ambiguous_player, get_profiles};
+use crate::{
+    arcana::{
+        iidx::{best_by_chart, get_level_charts, get_score_history, Best, Chart, Profile},
+        PlayStyle, Result,
+    },
+    handlers::arcana::{error_reply, ArcanaContext},
+};
+
+/// Number of the biggest EX score gaps to show
+const MAX_GAPS: usize = 5;
+/// Maximum number of charts played by only one player to list
+const MAX_UNSHARED: usize = 10;
+
+/// Wins, losses and draws of the first player
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Record {
+    wins: usize,
+    losses: usize,
+    draws: usize,
+}
+
+impl Record {
+    fn add(&mut self, ordering: Ordering) {
+        match ordering {
+            Ordering::Greater => self.wins += 1,
+            Ordering::Less => self.losses += 1,
+            Ordering::Equal => self.draws += 1,
+        }
+    }
+}
+
+/// Bests of two players compared chart by chart
+#[derive(Debug, Default)]
+struct Versus<'a> {
+    ex_score: Record,
+    lamp: Record,
+    /// Charts played by both, the biggest EX score gap first
+    both: Vec<(&'a Best, &'a Best)>,
+    /// Charts played by only one of them, the highest EX score first
+    only_a: Vec<&'a Best>,
+    only_b: Vec<&'a Best>,
+}
+
+fn compare<'a>(a: &'a HashMap<String, Best>, b: &'a HashMap<String, Best>) -> Versus<'a> {
+    let mut versus = Versus::default();
+    for (chart_id, best_a) in a {
+        match b.get(chart_id) {
+            Some(best_b) => {
+                versus
+                    .ex_score
+                    .add(best_a.ex_score.value.cmp(&best_b.ex_score.value));
+                versus.lamp.add(best_a.lamp.value.cmp(&best_b.lamp.value));
+                versus.both.push((best_a, best_b));
+            }
+            None => versus.only_a.push(best_a),
+        }
+    }
+    versus.only_b = b
+        .values()
+        .filter(|best| !a.contains_key(&best.chart_id))
+        .collect();
+    versus
+        .both
+        .sort_by_key(|(a, b)| Reverse(a.ex_score.value.abs_diff(b.ex_score.value)));
+    for only in [&mut versus.only_a, &mut versus.only_b] {
+        only.sort_by_key(|best| Reverse(best.ex_score.value));
+    }
+    versus
+}
+
+/// Render the comparison, naming charts with `label` by chart ID
+fn render_versus(
+    name_a: &str,
+    name_b: &str,
+    header: &str,
+    versus: &Versus,
+    label: impl Fn(&str) -> String,
+) -> String {
+    let table = format!(
+        "{:<8} {:>4} {:>4} {:>4}\n{:<8} {:>4} {:>4} {:>4}\n{:<8} {:>4} {:>4} {:>4}",
+        "",
+        "WIN",
+        "LOSE",
+        "DRAW",
+        "EX SCORE",
+        versus.ex_score.wins,
+        versus.ex_score.losses,
+        versus.ex_score.draws,
+        "LAMP",
+        versus.lamp.wins,
+        versus.lamp.losses,
+        versus.lamp.draws,
+    );
+
+    let gaps = versus
+        .both
+        .iter()
+        .take(MAX_GAPS)
+        .filter(|(a, b)| a.ex_score.value != b.ex_score.value)
+        .map(|(a, b)| {
+            format!(
+                "{}\n{} - {} ({:+})",
+                label(&a.chart_id),
+                a.ex_score.value,
+                b.ex_score.value,
+                i64::from(a.ex_score.value) - i64::from(b.ex_score.value)
+            )
+        })
+        .collect::<Vec<String>>();
+
+    let unshared = |name: &str, bests: &[&Best]| {
+        let mut charts = bests
+            .iter()
+            .take(MAX_UNSHARED)
+            .map(|b| label(&b.chart_id))
+            .collect::<Vec<String>>();
+        let more = bests.len().saturating_sub(MAX_UNSHARED);
+        if more > 0 {
+            charts.push(format!("and {} more", more));
+        }
+        format!(
+            "{}\n{}",
+            bold(&escape(&format!(
+                "Only played by {} ({}):",
+                name,
+                bests.len()
+            ))),
+            escape(&charts.join("\n"))
+        )
+    };
+
+    let mut sections = vec![
+        bold(&escape(&format!("{} vs {}{}", name_a, name_b, header))),
+        format!(
+            "{}\n{}",
+            escape(&format!("Both played: {}", versus.both.len())),
+            code_block(&table)
+        ),
+    ];
+    if !gaps.is_empty() {
+        sections.push(format!(
+            "{}\n{}",
+            bold("Biggest gaps:"),
+            escape(&gaps.join("\n"))
+        ));
+    }
+    if !versus.only_a.is_empty() {
+        sections.push(unshared(name_a, &versus.only_a));
+    }
+    if !versus.only_b.is_empty() {
+        sections.push(unshared(name_b, &versus.only_b));
+    }
+    sections.join("\n\n")
+}
+
+/// The only profile matching the player, or the reply when there is none or
+/// several
+fn only_profile(player: &str, mut profiles: Vec<Profile>) -> std::result::Result<Profile, String> {
+    match profiles.len() {
+        0 => Err(escape(&format!("{} not found", player))),
+        1 => Ok(profiles.remove(0)),
+        _ => Err(ambiguous_player(player, &profiles)),
+    }
+}
+
+async fn vs_output(
+    ctx: &ArcanaContext,
+    version: u32,
+    player_a: &str,
+    player_b: &str,
+    level: Option<u32>,
+) -> Result<String> {
+    let (a, b) = tokio::try_join!(
+        get_profiles(&ctx.client, version, player_a),
+        get_profiles(&ctx.client, version, player_b),
+    )?;
+    let a = match only_profile(player_a, a) {
+        Ok(a) => a,
+        Err(reply) => return Ok(reply),
+    };
+    let b = match only_profile(player_b, b) {
+        Ok(b) => b,
+        Err(reply) => return Ok(reply),
+    };
+    let (plays_a, plays_b) = tokio::try_join!(
+        get_score_history(&ctx.client, version, &a.id),
+        get_score_history(&ctx.client, version, &b.id),
+    )?;
+    let (mut bests_a, mut bests_b) = (best_by_chart(&plays_a), best_by_chart(&plays_b));
+
+    let header = match level {
+        Some(level) => {
+            let (sp, dp) = tokio::try_join!(
+                get_level_charts(&ctx.client, version, PlayStyle::Single, level),
+                get_level_charts(&ctx.client, version, PlayStyle::Double, level),
+            )?;
+            let chart_ids = sp.iter().chain(&dp).map(|c| &c.id).collect::<HashSet<_>>();
+            bests_a.retain(|id, _| chart_ids.contains(id));
+            bests_b.retain(|id, _| chart_ids.contains(id));
+            format!(" (LV{})", level)
+        }
+        None => String::new(),
+    };
+    let versus = compare(&bests_a, &bests_b);
+
+    // only look up the charts and songs shown
+    let music_ids = plays_a
+        .iter()
+        .chain(&plays_b)
+        .map(|p| (p.chart_id.as_str(), p.music_id.as_str()))
+        .collect::<HashMap<_, _>>();
+    let shown = versus
+        .both
+        .iter()
+        .take(MAX_GAPS)
+        .map(|(a, _)| *a)
+        .chain(versus.only_a.iter().take(MAX_UNSHARED).copied())
+        .chain(versus.only_b.iter().take(MAX_UNSHARED).copied())
+        .filter_map(|b| music_ids.get(b.chart_id.as_str()).copied())
+        .collect::<HashSet<_>>();
+    let mut charts: HashMap<String, (String, Chart)> = HashMap::new();
+    for music_id in shown {
+        let title = ctx
+            .cache
+            .music(&ctx.client, version, music_id)
+            .await?
+            .map_or(music_id.to_owned(), |m| m.title);
+        for chart in ctx.cache.charts(&ctx.client, version, music_id).await? {
+            charts.insert(chart.id.clone(), (title.clone(), chart));
+        }
+    }
+
+    Ok(render_versus(
+        &a.dj_name,
+        &b.dj_name,
+        &header,
+        &versus,
+        |chart_id| match charts.get(chart_id) {
+            Some((title, c)) => format!(
+                "{} [{}{}{}]",
+                title,
+                c.play_style,
+                c.difficulty.short(),
+                c.rating
+            ),
+            None => chart_id.to_owned(),
+        },
+    ))
+}
+
+/// Compare the personal bests of two players on the charts they played
+pub async fn vs(
+    bot: Bot,
+    message: Message,
+    ctx: &ArcanaContext,
+    version: u32,
+    player_a: &str,
+    player_b: &str,
+    level: Option<u32>,
+) -> std::result::Result<(), Box<dyn Error + Send + Sync>> {
+    let output = vs_output(ctx, version, player_a, player_b, level)
+        .await
+        .unwrap_or_else(|e| error_reply(&e));
+    bot.send_message(message.chat.id, output)
+        .parse_mode(ParseMode::MarkdownV2)
+        .reply_parameters(ReplyParameters::new(message.id))
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arcana::iidx::{Lamp, ScoreHistory};
+
+    fn play(chart_id: &str, ex_score: u32, lamp: Lamp) -> ScoreHistory {
+        ScoreHistory {
+            id: chart_id.to_owned(),
+            chart_id: chart_id.to_owned(),
+            ex_score,
+            lamp,
+            miss_count: None,
+            music_id: "G6vGmV2XC2Y".to_owned(),
+            profile_id: "C3PttzgAx6F".to_owned(),
+            raised: false,
+            status: lamp,
+            timestamp: "2021-10-03T13:01:52Z".to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_compare() {
+        let a = best_by_chart(&[
+            play("8bWq2LkPz0x", 2741, Lamp::HardClear),
+            play("3rN9ctmLu3V", 1800, Lamp::Clear),
+            play("Qm7rT1vYx5e", 1500, Lamp::Clear),
+            play("Zp4dW8nBc1k", 900, Lamp::Failed),
+        ]);
+        let b = best_by_chart(&[
+            play("8bWq2LkPz0x", 2650, Lamp::HardClear),
+            play("3rN9ctmLu3V", 1900, Lamp::FullCombo),
+            play("Qm7rT1vYx5e", 1500, Lamp::EasyClear),
+            play("Lx2cV6mNq9t", 2000, Lamp::Clear),
+        ]);
+
+        let versus = compare(&a, &b);
+        assert_eq!(
+            versus.ex_score,
+            Record {
+                wins: 1,
+                losses: 1,
+                draws: 1
+            }
+        );
+        assert_eq!(
+            versus.lamp,
+            Record {
+                wins: 1,
+                losses: 1,
+                draws: 1
+            }
+        );
+        assert_eq!(versus.both[0].0.chart_id, "3rN9ctmLu3V");
+        assert_eq!(versus.only_a[0].chart_id, "Zp4dW8nBc1k");
+        assert_eq!(versus.only_b[0].chart_id, "Lx2cV6mNq9t");
+
+        let output = render_versus("ORIGIN", "RIVAL", " (LV12)", &versus, |id| {
+            format!("Mind Mapping [{}]", id)
+        });
+        assert!(output.starts_with("*ORIGIN vs RIVAL \\(LV12\\)*"));
+        assert!(output.contains("EX SCORE    1    1    1"));
+        assert!(output.contains(
+            "1800 \\- 1900 \\(\\-100\\)\nMind Mapping \\[8bWq2LkPz0x\\]\n2741 \\- 2650 \\(\\+91\\)"
+        ));
+        assert!(output.contains("Only played by RIVAL \\(1\\):*\nMind Mapping \\[Lx2cV6mNq9t\\]"));
+    }
+}
@@ -1 +1,280 @@
+use std::{collections::HashMap, error::Error};
+use teloxide::{
+    prelude::*,
+    types::{ParseMode, ReplyParameters},
+    utils::markdown::{bold, escape},
+};
+
+use crate::{
+    arcana::{
+        find_profiles, get_by_ids, get_catalogue, get_music_charts, get_recent,
+        iidx::MetadataCache, plays, ArcanaClient, ArcanaError, GameChart, GameMusic, GameProfile,
+        GameScore, Result,
+    },
+    fuzzy::ranked_matches,
+    links::Links,
+};
+
+/// Maximum number of songs a search returns
+const MAX_SEARCH_RESULTS: usize = 5;
+
+/// Arcana client and the state shared by the handlers using it
+#[derive(Debug, Clone)]
+pub struct ArcanaContext {
+    pub client: ArcanaClient,
+    pub cache: MetadataCache,
+    pub links: Links,
+}
+
+/// Game version and player name/ID given in a command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Player {
+    pub version: u32,
+    pub param: String,
+}
+
+/// Find the song with the title, otherwise the best fuzzy matches by the
+/// keys, the first of which is the title
+pub fn search_catalogue<'a, M, const K: usize>(
+    catalogue: &'a [M],
+    query: &str,
+    keys: impl Fn(&M) -> [&str; K],
+) -> Vec<&'a M> {
+    if let Some(m) = catalogue
+        .iter()
+        .find(|m| keys(m)[0].eq_ignore_ascii_case(query))
+    {
+        return vec![m];
+    }
+    ranked_matches(catalogue, query, keys, MAX_SEARCH_RESULTS)
+}
+
+/// Search all music of the game version by title or artist
+pub async fn search_music<M: GameMusic>(
+    client: &ArcanaClient,
+    version: u32,
+    query: &str,
+) -> Result<Vec<M>> {
+    let catalogue = get_catalogue::<M>(client, version).await?;
+    Ok(
+        search_catalogue(&catalogue, query, |m| [m.title(), m.artist()])
+            .into_iter()
+            .cloned()
+            .collect(),
+    )
+}
+
+/// Ask for the in-game ID of a player matching several profiles
+fn ambiguous_profiles<P: GameProfile>(player: &str, profiles: &[P]) -> String {
+    escape(&format!(
+        "Several profiles match {}, please give the {}:\n{}",
+        player,
+        P::ID_FIELD.replace('_', " ").to_uppercase(),
+        profiles
+            .iter()
+            .map(|p| format!("{} ({})", p.name(), p.game_id()))
+            .collect::<Vec<String>>()
+            .join("\n")
+    ))
+}
+
+async fn profile_output<P: GameProfile>(
+    client: &ArcanaClient,
+    player: Player,
+    render: impl Fn(&P) -> String,
+) -> Result<String> {
+    let profiles: Vec<P> = find_profiles(client, player.version, &player.param).await?;
+    if profiles.is_empty() {
+        return Ok("Not found".to_owned());
+    }
+    Ok(profiles
+        .iter()
+        .map(render)
+        .collect::<Vec<String>>()
+        .join("\n------\n"))
+}
+
+/// Show the profiles of the player, each rendered by the game
+pub async fn profile<P: GameProfile>(
+    bot: Bot,
+    message: Message,
+    client: &ArcanaClient,
+    player: Player,
+    render: impl Fn(&P) -> String,
+) -> std::result::Result<(), Box<dyn Error + Send + Sync>> {
+    let output = profile_output(client, player, render).await;
+    reply(bot, message, output).await
+}
+
+async fn music_output<M: GameMusic>(
+    client: &ArcanaClient,
+    version: u32,
+    title: &str,
+    render: impl Fn(&M, &[M::Chart]) -> String,
+) -> Result<String> {
+    // take the best match when several songs match the title
+    let Some(m) = search_music::<M>(client, version, title)
+        .await?
+        .into_iter()
+        .next()
+    else {
+        return Ok("Song not found".to_owned());
+    };
+    let charts = get_music_charts::<M>(client, version, m.id()).await?;
+
+    Ok(render(&m, &charts))
+}
+
+/// Show the song with the title and its charts, rendered by the game
+pub async fn music<M: GameMusic>(
+    bot: Bot,
+    message: Message,
+    client: &ArcanaClient,
+    version: u32,
+    title: &str,
+    render: impl Fn(&M, &[M::Chart]) -> String,
+) -> std::result::Result<(), Box<dyn Error + Send + Sync>> {
+    let output = music_output(client, version, title, render).await;
+    reply(bot, message, output).await
+}
+
+/// List the rendered recent plays under the name of the player
+fn render_recent(name: &str, plays: Vec<String>) -> String {
+    format!(
+        "{}\n\n{}",
+        bold(&escape(&format!("Recent plays of {}", name))),
+        if plays.is_empty() {
+            "No plays".to_owned()
+        } else {
+            plays.join("\n\n")
+        }
+    )
+}
+
+async fn recent_output<P: GameProfile, M: GameMusic, S: GameScore>(
+    client: &ArcanaClient,
+    player: Player,
+    n: u32,
+    render_play: impl Fn(&S, Option<&M>, Option<&M::Chart>) -> String,
+) -> Result<String> {
+    let version = player.version;
+    let mut profiles = find_profiles::<P>(client, version, &player.param).await?;
+    if profiles.len() > 1 {
+        return Ok(ambiguous_profiles(&player.param, &profiles));
+    }
+    let Some(p) = profiles.pop() else {
+        return Ok("Not found".to_owned());
+    };
+    let plays: Vec<S> = get_recent(client, P::GAME, version, plays(p.id()), n).await?;
+    if plays.is_empty() {
+        return Ok(render_recent(p.name(), Vec::new()));
+    }
+
+    let music_ids = plays.iter().map(|p| p.music_id()).collect::<Vec<_>>();
+    let chart_ids = plays.iter().map(|p| p.chart_id()).collect::<Vec<_>>();
+    let music: HashMap<String, M> = get_by_ids::<M>(client, P::GAME, version, "music/", &music_ids)
+        .await?
+        .into_iter()
+        .map(|m| (m.id().to_owned(), m))
+        .collect();
+    let charts: HashMap<String, M::Chart> =
+        get_by_ids::<M::Chart>(client, P::GAME, version, "charts/", &chart_ids)
+            .await?
+            .into_iter()
+            .map(|c| (c.id().to_owned(), c))
+            .collect();
+
+    Ok(render_recent(
+        p.name(),
+        plays
+            .iter()
+            .map(|play| {
+                render_play(
+                    play,
+                    music.get(play.music_id()),
+                    charts.get(play.chart_id()),
+                )
+            })
+            .collect(),
+    ))
+}
+
+/// Show the latest `n` plays of the player, rendered by the game
+pub async fn recent<P: GameProfile, M: GameMusic, S: GameScore>(
+    bot: Bot,
+    message: Message,
+    client: &ArcanaClient,
+    player: Player,
+    n: u32,
+    render_play: impl Fn(&S, Option<&M>, Option<&M::Chart>) -> String,
+) -> std::result::Result<(), Box<dyn Error + Send + Sync>> {
+    let output = recent_output::<P, M, S>(client, player, n, render_play).await;
+    reply(bot, message, output).await
+}
+
+/// Reply to the command with the MarkdownV2 output, or the error
+pub async fn reply(
+    bot: Bot,
+    message: Message,
+    output: Result<String>,
+) -> std::result::Result<(), Box<dyn Error + Send + Sync>> {
+    bot.send_message(message.chat.id, output.unwrap_or_else(|e| error_reply(&e)))
+        .parse_mode(ParseMode::MarkdownV2)
+        .reply_parameters(ReplyParameters::new(message.id))
+        .await?;
+
+    Ok(())
+}
+
+/// Turn an Arcana error into a MarkdownV2 reply for the user
+pub fn error_reply(error: &ArcanaError) -> String {
+    log::warn!("{}", error);
+    escape(match error {
+        ArcanaError::Unauthorized => {
+            "The bot is not authorized on Arcana, please contact the bot admin."
+        }
+        ArcanaError::NotFound => "Not found on Arcana, please check the game version.",
+        ArcanaError::RateLimited => "Arcana is rate limiting the bot, please try again later.",
+        ArcanaError::Unavailable(_) | ArcanaError::Request(_) => {
+            "Arcana is unavailable now, please try again later."
+        }
+        ArcanaError::Status(_) | ArcanaError::InvalidUrl(_) | ArcanaError::Decode { .. } => {
+            "Arcana returned an unexpected response."
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ambiguous_profiles() {
+        let profile = |name: &str, sdvx_id: &str| crate::arcana::sdvx::Profile {
+            id: String::new(),
+            name: name.to_owned(),
+            sdvx_id: sdvx_id.to_owned(),
+        };
+        assert_eq!(
+            ambiguous_profiles("ORIGIN", &[profile("ORIGIN", "SV-1234-5678"), profile("ORIGIN", "SV-8765-4321")]),
+            "Several profiles match ORIGIN, please give the SDVX ID:\nORIGIN \\(SV\\-1234\\-5678\\)\nORIGIN \\(SV\\-8765\\-4321\\)"
+        );
+    }
+
+    #[test]
+    fn test_render_recent() {
+        assert_eq!(
+            render_recent("ORIGIN", Vec::new()),
+            "*Recent plays of ORIGIN*\n\nNo plays"
+        );
+        assert_eq!(
+            render_recent("ORIGIN", vec!["*A*".to_owned(), "*B*".to_owned()]),
+            "*Recent plays of ORIGIN*\n\n*A*\n\n*B*"
+        );
+    }
+}
+
+pub mod ddr;
 pub mod iidx;
+pub mod sdvx;
+pub mod versions;
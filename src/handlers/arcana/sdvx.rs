@@ -0,0 +1,78 @@
+use teloxide::utils::markdown::*;
+
+use crate::{
+    arcana::sdvx::{Chart, Music, Profile, ScoreHistory},
+    handlers::sdvx::Grade,
+};
+
+pub fn render_profile(p: &Profile) -> String {
+    format!("NAME: {}\nSDVX ID: {}", escape(&p.name), escape(&p.sdvx_id))
+}
+
+pub fn render_music(m: &Music, charts: &[Chart]) -> String {
+    let mut charts = charts.iter().collect::<Vec<_>>();
+    charts.sort_by_key(|c| c.level);
+    let mut table = format!("{:<10} {:>2}", "", "LV");
+    for c in charts {
+        table.push_str(&format!("\n{:<10} {:>2}", c.difficulty, c.level));
+    }
+
+    format!(
+        "{}\n{}\n{}",
+        bold(&escape(&m.title)),
+        escape(&m.artist),
+        code_block(&table)
+    )
+}
+
+pub fn render_play(play: &ScoreHistory, music: Option<&Music>, chart: Option<&Chart>) -> String {
+    let title = music.map_or(play.music_id.as_str(), |m| m.title.as_str());
+    let chart = chart.map_or(String::new(), |c| {
+        format!(" [{} {}]", c.difficulty, c.level)
+    });
+    format!(
+        "{}\n{}",
+        bold(&escape(&format!("{}{}", title, chart))),
+        escape(&format!(
+            "{} {} {} ({})",
+            play.score,
+            Grade::of(play.score),
+            play.lamp.replace('_', " "),
+            play.timestamp.split('T').next().unwrap_or("")
+        ))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_play() {
+        let play = ScoreHistory {
+            id: "Tn6wE2rQs9a".to_owned(),
+            chart_id: "Bk3pX7mLd2q".to_owned(),
+            lamp: "ULTIMATE_CHAIN".to_owned(),
+            music_id: "Rf8cY1vNw4z".to_owned(),
+            profile_id: "Hq2vNs8TkLw".to_owned(),
+            score: 9_912_345,
+            timestamp: "2022-05-14T09:41:12Z".to_owned(),
+        };
+        let music = Music {
+            id: "Rf8cY1vNw4z".to_owned(),
+            artist: "Camellia".to_owned(),
+            title: "Xroniàl Xéro".to_owned(),
+        };
+        let chart = Chart {
+            id: "Bk3pX7mLd2q".to_owned(),
+            difficulty: "EXHAUST".to_owned(),
+            level: 18,
+            music_id: "Rf8cY1vNw4z".to_owned(),
+        };
+
+        let output = render_play(&play, Some(&music), Some(&chart));
+        assert!(output.contains("*Xroniàl Xéro \\[EXHAUST 18\\]*"));
+        assert!(output.contains("9912345 S ULTIMATE CHAIN \\(2022\\-05\\-14\\)"));
+        assert!(render_play(&play, None, None).starts_with("*Rf8cY1vNw4z*"));
+    }
+}
@@ -0,0 +1,57 @@
+use std::error::Error;
+use teloxide::{prelude::*, types::ParseMode, types::ReplyParameters, utils::markdown::*};
+
+use crate::{arcana::Game, DEFAULT_IIDX_VERSION};
+
+fn render_versions(default_iidx_version: u32) -> String {
+    [Game::Iidx, Game::Sdvx, Game::Ddr]
+        .iter()
+        .map(|game| {
+            let versions = game
+                .versions()
+                .iter()
+                .map(|v| {
+                    let mut line = format!("{:>2} {}", v.number, v.name);
+                    if !v.aliases.is_empty() {
+                        line.push_str(&format!(" ({})", v.aliases.join(", ")));
+                    }
+                    if *game == Game::Iidx && v.number == default_iidx_version {
+                        line.push_str(" *");
+                    }
+                    line
+                })
+                .collect::<Vec<String>>()
+                .join("\n");
+            format!(
+                "{}\n{}",
+                bold(&escape(&game.to_string())),
+                code_block(&versions)
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// List the known versions of each game, marking the default IIDX version
+pub async fn versions(bot: Bot, message: Message) -> Result<(), Box<dyn Error + Send + Sync>> {
+    bot.send_message(message.chat.id, render_versions(*DEFAULT_IIDX_VERSION))
+        .parse_mode(ParseMode::MarkdownV2)
+        .reply_parameters(ReplyParameters::new(message.id))
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_versions() {
+        let output = render_versions(28);
+        assert!(output.starts_with("*IIDX*\n```\n"));
+        assert!(output.contains("28 BISTROVER (BISTRO, ビストロオーバー) *\n"));
+        assert!(output.contains("*SOUND VOLTEX*"));
+        assert!(output.contains(" 7 ∇ (NABLA, ナブラ)"));
+    }
+}
@@ -0,0 +1,92 @@
+use reqwest::get;
+use std::error::Error;
+use teloxide::{
+    prelude::*,
+    types::{ParseMode, ReplyParameters},
+    utils::markdown::*,
+};
+use tokio::fs;
+
+use crate::{
+    arcana::iidx::Difficulty,
+    bpi::{calc_bpi, BpiChart, BpiTable},
+    fuzzy::best_matches,
+    handlers::admin::is_admin,
+    BPI_PATH,
+};
+
+pub async fn bpi(
+    bot: Bot,
+    message: Message,
+    title: &str,
+    difficulty: Difficulty,
+    ex_score: u32,
+    table: &BpiTable,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let table = table.read().await;
+    let charts = table
+        .iter()
+        .filter(|c| c.difficulty == difficulty)
+        .cloned()
+        .collect::<Vec<BpiChart>>();
+    let output = best_matches(&charts, title, |c| &c.title)
+        .into_iter()
+        .map(|chart| match calc_bpi(chart, ex_score) {
+            Some(bpi) => format!(
+                "{}\n{} {}/{}\nWR: {} Kaiden Avg: {}\n\nBPI: {}",
+                bold(&escape(&chart.title)),
+                chart.difficulty,
+                ex_score,
+                chart.notes * 2,
+                chart.wr,
+                chart.avg,
+                escape(&format!("{:.2}", bpi)),
+            ),
+            None => format!(
+                "{}\n{}",
+                bold(&escape(&chart.title)),
+                escape("Invalid EX score or reference data!")
+            ),
+        })
+        .collect::<Vec<String>>()
+        .join("\n------\n");
+    bot.send_message(
+        message.chat.id,
+        if output.is_empty() {
+            "Not found".to_owned()
+        } else {
+            output
+        },
+    )
+    .parse_mode(ParseMode::MarkdownV2)
+    .reply_parameters(ReplyParameters::new(message.id))
+    .await?;
+
+    Ok(())
+}
+
+/// Replace the BPI reference data with the dataset at the given URL
+pub async fn bpi_import(
+    bot: Bot,
+    message: Message,
+    url: &str,
+    table: &BpiTable,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if !is_admin(&message) {
+        bot.send_message(message.chat.id, "Permission denied!")
+            .reply_parameters(ReplyParameters::new(message.id))
+            .await?;
+        return Ok(());
+    }
+
+    let charts = get(url).await?.json::<Vec<BpiChart>>().await?;
+    fs::write(BPI_PATH, serde_json::to_vec_pretty(&charts)?).await?;
+    let count = charts.len();
+    *table.write().await = charts;
+
+    bot.send_message(message.chat.id, format!("Imported {} charts!", count))
+        .reply_parameters(ReplyParameters::new(message.id))
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,17 @@
+use std::error::Error;
+use teloxide::{prelude::*, types::ReplyParameters};
+
+use crate::calculator::ScoreCalculator;
+
+/// Reply with the result of a calculator
+pub async fn calculate<C: ScoreCalculator>(
+    bot: Bot,
+    message: Message,
+    input: &str,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    bot.send_message(message.chat.id, C::evaluate(input))
+        .reply_parameters(ReplyParameters::new(message.id))
+        .await?;
+
+    Ok(())
+}
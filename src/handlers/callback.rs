@@ -0,0 +1,235 @@
+use std::{error::Error, fmt, str::FromStr};
+use teloxide::{prelude::*, types::UserId};
+
+use crate::{
+    arcana::PlayStyle,
+    bpi::BpiTable,
+    handlers::arcana::{
+        iidx::{self, link, music, recent, recent::RecentPage},
+        ArcanaContext,
+    },
+};
+
+/// Data carried by inline keyboard buttons, limited to 64 bytes by Telegram
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Callback {
+    /// Show the charts of a song from `/iidxmusic` in a play style
+    Music {
+        version: u32,
+        music_id: String,
+        play_style: PlayStyle,
+    },
+    /// Turn to a page of `/iidxrecent`
+    Recent(RecentPage),
+    /// Confirm linking the user to a profile from `/iidxlink`
+    Link {
+        user: UserId,
+        version: u32,
+        profile_id: String,
+    },
+    /// Cancel linking the user
+    CancelLink { user: UserId },
+    /// Run the user's command with one of the profiles matching the player
+    PickProfile {
+        user: UserId,
+        version: u32,
+        iidx_id: String,
+    },
+}
+
+impl Callback {
+    /// The only user allowed to press the button, if any
+    fn owner(&self) -> Option<UserId> {
+        match self {
+            Callback::Link { user, .. }
+            | Callback::CancelLink { user }
+            | Callback::PickProfile { user, .. } => Some(*user),
+            Callback::Music { .. } | Callback::Recent(_) => None,
+        }
+    }
+}
+
+fn parse_user(s: &str) -> Result<UserId, String> {
+    s.parse()
+        .map(UserId)
+        .map_err(|_| format!("invalid user: {}", s))
+}
+
+impl fmt::Display for Callback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Callback::Music {
+                version,
+                music_id,
+                play_style,
+            } => write!(f, "iidxmusic:{}:{}:{}", version, music_id, play_style),
+            Callback::Recent(page) => write!(f, "iidxrecent:{}", page),
+            Callback::Link {
+                user,
+                version,
+                profile_id,
+            } => write!(f, "iidxlink:{}:{}:{}", user, version, profile_id),
+            Callback::CancelLink { user } => write!(f, "iidxlinkcancel:{}", user),
+            Callback::PickProfile {
+                user,
+                version,
+                iidx_id,
+            } => write!(f, "iidxpick:{}:{}:{}", user, version, iidx_id),
+        }
+    }
+}
+
+impl FromStr for Callback {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, data) = s
+            .split_once(':')
+            .ok_or_else(|| format!("invalid callback: {}", s))?;
+        match kind {
+            "iidxmusic" => {
+                let mut parts = data.split(':');
+                match (parts.next(), parts.next(), parts.next()) {
+                    (Some(version), Some(music_id), Some(play_style)) => Ok(Callback::Music {
+                        version: version
+                            .parse()
+                            .map_err(|_| format!("invalid version: {}", version))?,
+                        music_id: music_id.to_owned(),
+                        play_style: play_style.parse()?,
+                    }),
+                    _ => Err(format!("invalid callback: {}", s)),
+                }
+            }
+            "iidxrecent" => Ok(Callback::Recent(data.parse()?)),
+            "iidxlink" => {
+                let mut parts = data.split(':');
+                match (parts.next(), parts.next(), parts.next()) {
+                    (Some(user), Some(version), Some(profile_id)) => Ok(Callback::Link {
+                        user: parse_user(user)?,
+                        version: version
+                            .parse()
+                            .map_err(|_| format!("invalid version: {}", version))?,
+                        profile_id: profile_id.to_owned(),
+                    }),
+                    _ => Err(format!("invalid callback: {}", s)),
+                }
+            }
+            "iidxlinkcancel" => Ok(Callback::CancelLink {
+                user: parse_user(data)?,
+            }),
+            "iidxpick" => {
+                let mut parts = data.split(':');
+                match (parts.next(), parts.next(), parts.next()) {
+                    (Some(user), Some(version), Some(iidx_id)) => Ok(Callback::PickProfile {
+                        user: parse_user(user)?,
+                        version: version
+                            .parse()
+                            .map_err(|_| format!("invalid version: {}", version))?,
+                        iidx_id: iidx_id.to_owned(),
+                    }),
+                    _ => Err(format!("invalid callback: {}", s)),
+                }
+            }
+            _ => Err(format!("invalid callback: {}", s)),
+        }
+    }
+}
+
+pub async fn callback(
+    bot: Bot,
+    query: CallbackQuery,
+    arcana: ArcanaContext,
+    bpi_table: BpiTable,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let callback = query.data.as_deref().map(str::parse::<Callback>);
+    if let Some(Ok(callback)) = &callback {
+        if callback.owner().is_some_and(|owner| owner != query.from.id) {
+            bot.answer_callback_query(&query.id)
+                .text("This button is not for you!")
+                .await?;
+            return Ok(());
+        }
+    }
+    bot.answer_callback_query(&query.id).await?;
+    // buttons on messages too old to be edited are ignored
+    let (Some(message), Some(callback)) = (&query.message, callback) else {
+        return Ok(());
+    };
+
+    match callback {
+        Ok(Callback::Music {
+            version,
+            music_id,
+            play_style,
+        }) => {
+            music::music_picked(
+                bot,
+                message,
+                &arcana,
+                &query.from,
+                version,
+                &music_id,
+                play_style,
+            )
+            .await?
+        }
+        Ok(Callback::Recent(page)) => recent::recent_page(bot, message, &arcana, &page).await?,
+        Ok(Callback::Link {
+            version,
+            profile_id,
+            ..
+        }) => {
+            link::link_confirmed(bot, message, &arcana, &query.from, version, &profile_id).await?
+        }
+        Ok(Callback::CancelLink { .. }) => link::link_cancelled(bot, message).await?,
+        Ok(Callback::PickProfile {
+            version, iidx_id, ..
+        }) => iidx::profile_picked(bot, message, &arcana, &bpi_table, version, &iidx_id).await?,
+        Err(e) => log::warn!("{}", e),
+    }
+    arcana.cache.flush().await;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_callback() {
+        let callback = Callback::Music {
+            version: 28,
+            music_id: "G6vGmV2XC2Y".to_owned(),
+            play_style: PlayStyle::Double,
+        };
+        assert_eq!(callback.to_string(), "iidxmusic:28:G6vGmV2XC2Y:DP");
+        assert_eq!(callback.to_string().parse(), Ok(callback));
+        assert!("iidxmusic:latest:G6vGmV2XC2Y:SP"
+            .parse::<Callback>()
+            .is_err());
+        assert!("iidxmusic:28:G6vGmV2XC2Y".parse::<Callback>().is_err());
+
+        let callback = Callback::Link {
+            user: UserId(42),
+            version: 28,
+            profile_id: "C3PttzgAx6F".to_owned(),
+        };
+        assert_eq!(callback.to_string(), "iidxlink:42:28:C3PttzgAx6F");
+        assert_eq!(callback.owner(), Some(UserId(42)));
+        assert_eq!(callback.to_string().parse(), Ok(callback));
+        assert_eq!(
+            "iidxlinkcancel:42".parse(),
+            Ok(Callback::CancelLink { user: UserId(42) })
+        );
+
+        let callback = Callback::PickProfile {
+            user: UserId(42),
+            version: 28,
+            iidx_id: "1234-5678".to_owned(),
+        };
+        assert_eq!(callback.to_string(), "iidxpick:42:28:1234-5678");
+        assert_eq!(callback.owner(), Some(UserId(42)));
+        assert_eq!(callback.to_string().parse(), Ok(callback));
+    }
+}
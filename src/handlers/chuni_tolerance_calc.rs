@@ -1,42 +1,73 @@
-use std::error::Error;
-use teloxide::{prelude::*, types::ReplyParameters};
+use teloxide::utils::command::ParseError;
+
+use crate::{calculator::ScoreCalculator, commands::next_str_into_u32};
 
 const MAX_SCORE: u32 = 1010000;
 
-pub async fn tolerance_calc(
-    bot: Bot,
-    message: Message,
-    notes: u32,
-    target: &str,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    let single = MAX_SCORE as f32 / notes as f32;
-    let single_justice = single / 1.01;
-    let single_attack = single / 2.02;
-
-    let (target, target_score) = match target.to_lowercase().as_str() {
-        "sss+" => ("SSS+", 1009000),
-        "sss" => ("SSS", 1007500),
-        "ss+" => ("SS+", 1005000),
-        _ => ("SS", 1000000),
-    };
-
-    let total_justice = (MAX_SCORE - target_score) as f32 / (single - single_justice);
-    let total_attack = (MAX_SCORE - target_score) as f32 / (single - single_attack);
-
-    bot.send_message(
-        message.chat.id,
+pub struct ChuniTolerance {
+    pub notes: u32,
+    pub target: String,
+}
+
+pub struct Tolerance {
+    pub target: &'static str,
+    pub justice: f32,
+    pub attack: f32,
+    pub justice_loss: f32,
+    pub attack_loss: f32,
+    pub miss_loss: f32,
+}
+
+impl ScoreCalculator for ChuniTolerance {
+    const NAME: &'static str = "chunitolerance";
+    const USAGE: &'static str = "/chunitolerance NOTES [SS/SS+/SSS/SSS+]";
+
+    type Output = Tolerance;
+
+    fn parse(input: &str) -> Result<Self, ParseError> {
+        let mut parts = input.split_whitespace();
+        let notes = next_str_into_u32(parts.next())?;
+        if notes == 0 {
+            return Err(ParseError::Custom("invalid notes".into()));
+        }
+
+        Ok(ChuniTolerance {
+            notes,
+            target: parts.next().unwrap_or("").to_owned(),
+        })
+    }
+
+    fn compute(&self) -> Self::Output {
+        let single = MAX_SCORE as f32 / self.notes as f32;
+        let single_justice = single / 1.01;
+        let single_attack = single / 2.02;
+
+        let (target, target_score) = match self.target.to_lowercase().as_str() {
+            "sss+" => ("SSS+", 1009000),
+            "sss" => ("SSS", 1007500),
+            "ss+" => ("SS+", 1005000),
+            _ => ("SS", 1000000),
+        };
+
+        Tolerance {
+            target,
+            justice: (MAX_SCORE - target_score) as f32 / (single - single_justice),
+            attack: (MAX_SCORE - target_score) as f32 / (single - single_attack),
+            justice_loss: single - single_justice,
+            attack_loss: single - single_attack,
+            miss_loss: single,
+        }
+    }
+
+    fn render(&self, output: &Self::Output) -> String {
         format!(
             "For target {} we can have {} justice(s) or {} attack(s)\nJustice: -{}, Attack: -{}, Miss: -{}",
-            target,
-            total_justice,
-            total_attack,
-            single - single_justice,
-            single - single_attack,
-            single,
-        ),
-    )
-    .reply_parameters(ReplyParameters::new(message.id))
-    .await?;
-
-    Ok(())
+            output.target,
+            output.justice,
+            output.attack,
+            output.justice_loss,
+            output.attack_loss,
+            output.miss_loss,
+        )
+    }
 }
@@ -0,0 +1,311 @@
+use teloxide::utils::command::ParseError;
+
+use crate::{calculator::ScoreCalculator, commands::next_str_into_u32};
+
+const MAX_SCORE: u32 = 1000000;
+/// Upper bound of steps or freezes, well above any real chart
+const MAX_ARROWS: u32 = 10000;
+
+/// Minimum money score of each rank
+const RANKS: [(u32, &str); 15] = [
+    (990000, "AAA"),
+    (950000, "AA+"),
+    (900000, "AA"),
+    (890000, "AA-"),
+    (850000, "A+"),
+    (800000, "A"),
+    (790000, "A-"),
+    (750000, "B+"),
+    (700000, "B"),
+    (690000, "B-"),
+    (650000, "C+"),
+    (600000, "C"),
+    (590000, "C-"),
+    (550000, "D+"),
+    (0, "D"),
+];
+
+/// Judgement counts of a play
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Judgements {
+    pub marvelous: u32,
+    pub perfect: u32,
+    pub great: u32,
+    pub good: u32,
+    pub miss: u32,
+    pub ok: u32,
+    pub ng: u32,
+}
+
+impl Judgements {
+    /// Money score, in which every step and freeze arrow is worth 1,000,000 / (STEPS + FREEZES)
+    pub fn money_score(&self) -> u32 {
+        let items = (self.marvelous + self.perfect + self.great + self.good + self.miss) as u64
+            + (self.ok + self.ng) as u64;
+        if items == 0 {
+            return 0;
+        }
+        let base = (MAX_SCORE as u64 * (self.marvelous + self.perfect + self.ok) as u64
+            + 600000 * self.great as u64
+            + 200000 * self.good as u64)
+            / items;
+        let penalty = 10 * (self.perfect + self.great + self.good) as u64;
+        (base.saturating_sub(penalty) / 10 * 10) as u32
+    }
+
+    pub fn ex_score(&self) -> u32 {
+        3 * self.marvelous + 2 * self.perfect + self.great + 3 * self.ok
+    }
+}
+
+pub fn rank(score: u32) -> &'static str {
+    RANKS.iter().find(|(border, _)| score >= *border).unwrap().1
+}
+
+fn parse_target(target: &str) -> Option<u32> {
+    target.parse::<u32>().ok().or_else(|| {
+        RANKS
+            .iter()
+            .find(|(_, r)| r.eq_ignore_ascii_case(target))
+            .map(|(border, _)| *border)
+    })
+}
+
+pub enum Mode {
+    Score(Judgements),
+    Tolerance(u32),
+}
+
+pub struct DdrScore {
+    pub steps: u32,
+    pub freezes: u32,
+    pub mode: Mode,
+}
+
+pub enum DdrResult {
+    Score {
+        money_score: u32,
+        ex_score: u32,
+        max_ex_score: u32,
+    },
+    Tolerance {
+        target: u32,
+        perfects: u32,
+        greats: u32,
+    },
+}
+
+impl DdrScore {
+    /// Maximum count of a single judgement that still keeps the target,
+    /// while the rest are MARVELOUS and OK
+    fn tolerance(&self, target: u32, judgement: impl Fn(u32) -> Judgements) -> u32 {
+        // the score only drops as the count grows, so binary search the last
+        // count keeping the target
+        let (mut low, mut high) = (0, self.steps);
+        while low < high {
+            let mid = low + (high - low).div_ceil(2);
+            if judgement(mid).money_score() >= target {
+                low = mid;
+            } else {
+                high = mid - 1;
+            }
+        }
+        low
+    }
+}
+
+impl ScoreCalculator for DdrScore {
+    const NAME: &'static str = "ddrscore";
+    const USAGE: &'static str =
+        "/ddrscore STEPS FREEZES MARVELOUS,PERFECT,GREAT,GOOD,MISS,OK,NG or /ddrscore STEPS FREEZES TARGET";
+
+    type Output = DdrResult;
+
+    /// Parse a DDR score command
+    fn parse(input: &str) -> Result<Self, ParseError> {
+        // The command should satisfy one of these patterns:
+        // /ddrscore STEPS FREEZES MARVELOUS,PERFECT,GREAT,GOOD,MISS,OK,NG
+        // /ddrscore STEPS FREEZES TARGET
+        //
+        // For example:
+        // /ddrscore 500 20 480,15,3,0,2,19,1
+        // /ddrscore 500 20 990000
+        let mut parts = input.split_whitespace();
+        let steps = next_str_into_u32(parts.next())?;
+        let freezes = next_str_into_u32(parts.next())?;
+        if steps > MAX_ARROWS || freezes > MAX_ARROWS {
+            return Err(ParseError::Custom(
+                format!("Steps and freezes should be at most {}!", MAX_ARROWS).into(),
+            ));
+        }
+        let last = parts.next().unwrap_or("");
+        let mode = if last.contains(',') {
+            let mut counts = [0; 7];
+            let mut summary = last.splitn(7, ',');
+            for count in counts.iter_mut() {
+                *count = next_str_into_u32(summary.next())?;
+            }
+            let [marvelous, perfect, great, good, miss, ok, ng] = counts;
+            // steps and freezes not covered by the judgements are MARVELOUS and OK
+            let exceeded =
+                || ParseError::Custom("Judgements exceed the step or freeze count!".into());
+            let judged_steps = [perfect, great, good, miss]
+                .into_iter()
+                .try_fold(marvelous, u32::checked_add)
+                .filter(|judged| *judged <= steps)
+                .ok_or_else(exceeded)?;
+            let judged_freezes = ok
+                .checked_add(ng)
+                .filter(|judged| *judged <= freezes)
+                .ok_or_else(exceeded)?;
+            Mode::Score(Judgements {
+                marvelous: marvelous + steps - judged_steps,
+                perfect,
+                great,
+                good,
+                miss,
+                ok: ok + freezes - judged_freezes,
+                ng,
+            })
+        } else {
+            Mode::Tolerance(
+                parse_target(last)
+                    .filter(|t| *t <= MAX_SCORE)
+                    .ok_or_else(|| ParseError::Custom("invalid target".into()))?,
+            )
+        };
+        if steps + freezes == 0 {
+            return Err(ParseError::Custom("invalid steps".into()));
+        }
+
+        Ok(DdrScore {
+            steps,
+            freezes,
+            mode,
+        })
+    }
+
+    fn compute(&self) -> Self::Output {
+        match self.mode {
+            Mode::Score(judgements) => DdrResult::Score {
+                money_score: judgements.money_score(),
+                ex_score: judgements.ex_score(),
+                max_ex_score: 3 * (self.steps + self.freezes),
+            },
+            Mode::Tolerance(target) => {
+                let all = Judgements {
+                    marvelous: self.steps,
+                    ok: self.freezes,
+                    ..Default::default()
+                };
+                DdrResult::Tolerance {
+                    target,
+                    perfects: self.tolerance(target, |count| Judgements {
+                        marvelous: self.steps - count,
+                        perfect: count,
+                        ..all
+                    }),
+                    greats: self.tolerance(target, |count| Judgements {
+                        marvelous: self.steps - count,
+                        great: count,
+                        ..all
+                    }),
+                }
+            }
+        }
+    }
+
+    fn render(&self, output: &Self::Output) -> String {
+        match output {
+            DdrResult::Score {
+                money_score,
+                ex_score,
+                max_ex_score,
+            } => format!(
+                "Score: {} {}\nEX Score: {}/{}",
+                money_score,
+                rank(*money_score),
+                ex_score,
+                max_ex_score
+            ),
+            DdrResult::Tolerance {
+                target,
+                perfects,
+                greats,
+            } => {
+                let single = MAX_SCORE as f32 / (self.steps + self.freezes) as f32;
+                format!(
+                    "For target {} we can have {} perfect(s) or {} great(s)\nPerfect: -10, Great: -{}, Good: -{}, Miss: -{}",
+                    target,
+                    perfects,
+                    greats,
+                    single * 0.4 + 10.0,
+                    single * 0.8 + 10.0,
+                    single,
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_money_score() {
+        let judgements = Judgements {
+            marvelous: 300,
+            ..Default::default()
+        };
+        assert_eq!(judgements.money_score(), MAX_SCORE);
+        assert_eq!(judgements.ex_score(), 900);
+
+        let judgements = Judgements {
+            marvelous: 280,
+            perfect: 15,
+            great: 3,
+            miss: 2,
+            ok: 20,
+            ..Default::default()
+        };
+        // (1000000 * 315 + 600000 * 3) / 320 - 10 * 18 = 989,820
+        assert_eq!(judgements.money_score(), 989820);
+        assert_eq!(rank(judgements.money_score()), "AA+");
+    }
+
+    #[test]
+    fn test_tolerance() {
+        let calc = DdrScore::parse("500 20 AAA").unwrap();
+        match calc.compute() {
+            DdrResult::Tolerance {
+                perfects, greats, ..
+            } => {
+                assert_eq!(perfects, 500);
+                assert_eq!(greats, 12);
+            }
+            _ => panic!(),
+        }
+
+        // the first count below the target is not tolerated
+        let calc = DdrScore::parse("500 20 990000").unwrap();
+        let judgements = |great| Judgements {
+            marvelous: 500 - great,
+            great,
+            ok: 20,
+            ..Default::default()
+        };
+        let greats = calc.tolerance(990000, judgements);
+        assert!(judgements(greats).money_score() >= 990000);
+        assert!(judgements(greats + 1).money_score() < 990000);
+    }
+
+    #[test]
+    fn test_parse_bounds() {
+        assert!(DdrScore::parse("4000000000 0 990000").is_err());
+        assert!(DdrScore::parse("500 4000000000 990000").is_err());
+        assert!(DdrScore::parse("500 20 4000000000,4000000000,0,0,0,0,0").is_err());
+        assert!(DdrScore::parse("500 20 0,0,0,0,0,4000000000,4000000000").is_err());
+        assert!(DdrScore::parse("10000 10000 990000").is_ok());
+    }
+}
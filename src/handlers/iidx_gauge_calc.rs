@@ -0,0 +1,327 @@
+use std::fmt;
+use teloxide::utils::command::ParseError;
+
+use crate::{calculator::ScoreCalculator, commands::next_str_into_u32};
+
+/// IIDX judgements, in the order they are given in a summary
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Judgement {
+    PGreat,
+    Great,
+    Good,
+    Bad,
+    Poor,
+}
+
+const JUDGEMENTS: [Judgement; 5] = [
+    Judgement::PGreat,
+    Judgement::Great,
+    Judgement::Good,
+    Judgement::Bad,
+    Judgement::Poor,
+];
+
+impl Judgement {
+    /// Parse a judgement from its sequence letter (P, G, D, B, X)
+    pub fn from_char(c: char) -> Option<Self> {
+        match c.to_ascii_uppercase() {
+            'P' => Some(Judgement::PGreat),
+            'G' => Some(Judgement::Great),
+            'D' => Some(Judgement::Good),
+            'B' => Some(Judgement::Bad),
+            'X' => Some(Judgement::Poor),
+            _ => None,
+        }
+    }
+}
+
+/// Judgements of a play, either in order or as PG/GR/GD/BD/POOR counts
+#[derive(Debug, Clone)]
+pub enum Judgements {
+    Sequence(Vec<Judgement>),
+    Summary([u32; 5]),
+}
+
+impl Judgements {
+    /// Turn the judgements into a sequence of exactly `notes` judgements.
+    ///
+    /// Counts of a summary are spread evenly across the chart, and notes
+    /// not covered by the judgements are taken as PGREATs.
+    pub fn into_sequence(self, notes: u32) -> Option<Vec<Judgement>> {
+        let mut sequence = match self {
+            Judgements::Sequence(sequence) => sequence,
+            Judgements::Summary(counts) => spread(counts),
+        };
+        let rest = (notes as usize).checked_sub(sequence.len())?;
+        sequence.extend(std::iter::repeat_n(Judgement::PGreat, rest));
+        Some(sequence)
+    }
+}
+
+/// Interleave judgement counts so that each kind is evenly distributed
+fn spread(counts: [u32; 5]) -> Vec<Judgement> {
+    let total = counts.iter().map(|&c| c as i64).sum::<i64>();
+    let mut used = [0i64; 5];
+    (1..=total)
+        .map(|i| {
+            // pick the judgement which is the furthest behind its share
+            let k = (0..5)
+                .max_by_key(|&k| (counts[k] as i64 * i - used[k] * total, -(k as i64)))
+                .unwrap();
+            used[k] += 1;
+            JUDGEMENTS[k]
+        })
+        .collect()
+}
+
+/// IIDX groove gauges
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gauge {
+    Easy,
+    Normal,
+    Hard,
+    ExHard,
+    Dan,
+}
+
+pub const GAUGES: [Gauge; 5] = [
+    Gauge::Easy,
+    Gauge::Normal,
+    Gauge::Hard,
+    Gauge::ExHard,
+    Gauge::Dan,
+];
+
+impl fmt::Display for Gauge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Gauge::Easy => write!(f, "EASY"),
+            Gauge::Normal => write!(f, "NORMAL"),
+            Gauge::Hard => write!(f, "HARD"),
+            Gauge::ExHard => write!(f, "EX-HARD"),
+            Gauge::Dan => write!(f, "DAN"),
+        }
+    }
+}
+
+impl Gauge {
+    /// Gauge at the start of the chart, in percent
+    fn initial(&self) -> f64 {
+        match self {
+            Gauge::Easy | Gauge::Normal => 22.0,
+            Gauge::Hard | Gauge::ExHard | Gauge::Dan => 100.0,
+        }
+    }
+
+    /// Whether the gauge fails as soon as it is emptied
+    fn survival(&self) -> bool {
+        matches!(self, Gauge::Hard | Gauge::ExHard | Gauge::Dan)
+    }
+
+    /// Gauge change of a single judgement at the current gauge value
+    fn delta(&self, judgement: Judgement, notes: u32, current: f64) -> f64 {
+        // total recovery of the groove gauge over the whole chart
+        let total = 7.605 * notes as f64 / (0.01 * notes as f64 + 6.5);
+        let recovery = total / notes as f64;
+        let (pgreat, good, bad, poor) = match self {
+            Gauge::Easy => (recovery, recovery / 2.0, -1.6, -4.8),
+            Gauge::Normal => (recovery, recovery / 2.0, -2.0, -6.0),
+            Gauge::Hard => (0.16, 0.0, -10.0, -10.0),
+            Gauge::ExHard => (0.16, 0.0, -18.0, -18.0),
+            Gauge::Dan => (0.16, 0.0, -1.5, -2.5),
+        };
+        let delta = match judgement {
+            Judgement::PGreat | Judgement::Great => pgreat,
+            Judgement::Good => good,
+            Judgement::Bad => bad,
+            Judgement::Poor => poor,
+        };
+        match self {
+            // HARD and DAN take reduced damage at 30% or below
+            Gauge::Hard if delta < 0.0 && current <= 30.0 => delta * 0.6,
+            Gauge::Dan if delta < 0.0 && current <= 30.0 => delta * 0.5,
+            _ => delta,
+        }
+    }
+
+    /// Minimum gauge value required to clear at the end of the chart
+    fn border(&self) -> f64 {
+        match self {
+            Gauge::Easy | Gauge::Normal => 80.0,
+            Gauge::Hard | Gauge::ExHard | Gauge::Dan => 0.0,
+        }
+    }
+}
+
+/// Result of a gauge simulation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GaugeResult {
+    pub gauge: Gauge,
+    pub value: f64,
+    pub cleared: bool,
+    /// The note where a survival gauge has been emptied
+    pub failed_at: Option<u32>,
+}
+
+/// Simulate a gauge over the given judgement sequence
+pub fn calc_gauge(gauge: Gauge, judgements: &[Judgement]) -> GaugeResult {
+    let notes = judgements.len() as u32;
+    let mut value = gauge.initial();
+    for (i, j) in judgements.iter().enumerate() {
+        value = (value + gauge.delta(*j, notes, value)).min(100.0);
+        if gauge.survival() {
+            if value <= 0.0 {
+                return GaugeResult {
+                    gauge,
+                    value: 0.0,
+                    cleared: false,
+                    failed_at: Some(i as u32 + 1),
+                };
+            }
+        } else {
+            value = value.max(2.0);
+        }
+    }
+
+    GaugeResult {
+        gauge,
+        value,
+        cleared: value >= gauge.border() && value > 0.0,
+        failed_at: None,
+    }
+}
+
+pub struct GaugeCalc {
+    pub sequence: Vec<Judgement>,
+}
+
+impl ScoreCalculator for GaugeCalc {
+    const NAME: &'static str = "iidxgauge";
+    const USAGE: &'static str =
+        "/iidxgauge NOTES PG,GR,GD,BD,POOR or /iidxgauge NOTES SEQUENCE of P/G/D/B/X";
+
+    type Output = Vec<GaugeResult>;
+
+    /// Parse an IIDX gauge command
+    fn parse(input: &str) -> Result<Self, ParseError> {
+        // The command should satisfy one of these patterns:
+        // /iidxgauge NOTES PG,GR,GD,BD,POOR
+        // /iidxgauge NOTES SEQUENCE
+        //
+        // For example:
+        // /iidxgauge 1500 1200,250,20,10,20
+        // /iidxgauge 8 PPGGDBXP
+        let mut parts = input.split_whitespace();
+        let notes = next_str_into_u32(parts.next())?;
+        if notes == 0 {
+            return Err(ParseError::Custom("The chart must have notes!".into()));
+        }
+        let judgements = parts.next().unwrap_or("");
+        let judgements = if judgements.contains(',') {
+            let mut counts = [0; 5];
+            let mut summary = judgements.splitn(5, ',');
+            for count in counts.iter_mut() {
+                *count = next_str_into_u32(summary.next())?;
+            }
+            Judgements::Summary(counts)
+        } else {
+            Judgements::Sequence(
+                judgements
+                    .chars()
+                    .map(|c| {
+                        Judgement::from_char(c)
+                            .ok_or_else(|| ParseError::Custom("invalid judgement".into()))
+                    })
+                    .collect::<Result<_, _>>()?,
+            )
+        };
+
+        match judgements.into_sequence(notes) {
+            Some(sequence) => Ok(GaugeCalc { sequence }),
+            None => Err(ParseError::Custom(
+                "Judgements exceed the note count!".into(),
+            )),
+        }
+    }
+
+    fn compute(&self) -> Self::Output {
+        GAUGES
+            .iter()
+            .map(|gauge| calc_gauge(*gauge, &self.sequence))
+            .collect()
+    }
+
+    fn render(&self, output: &Self::Output) -> String {
+        let notes = self.sequence.len();
+        output
+            .iter()
+            .map(|result| match result.failed_at {
+                Some(note) => format!("{}: FAILED at note {}/{}", result.gauge, note, notes),
+                None => format!(
+                    "{}: {:.1}% {}",
+                    result.gauge,
+                    result.value,
+                    if result.cleared { "CLEAR" } else { "FAILED" }
+                ),
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spread() {
+        let sequence = spread([2, 1, 0, 0, 1]);
+        assert_eq!(sequence.len(), 4);
+        assert_eq!(
+            sequence.iter().filter(|j| **j == Judgement::PGreat).count(),
+            2
+        );
+        assert_eq!(
+            sequence.iter().filter(|j| **j == Judgement::Poor).count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        let error = |input| match GaugeCalc::parse(input) {
+            Err(ParseError::Custom(e)) => e.to_string(),
+            _ => panic!("{} should not parse", input),
+        };
+        assert_eq!(error("0 PPG"), "The chart must have notes!");
+        assert_eq!(error("2 PPG"), "Judgements exceed the note count!");
+        assert_eq!(GaugeCalc::parse("4 PPG").unwrap().sequence.len(), 4);
+    }
+
+    #[test]
+    fn test_calc_gauge() {
+        let all_pgreat = Judgements::Summary([1000, 0, 0, 0, 0])
+            .into_sequence(1000)
+            .unwrap();
+        for gauge in GAUGES {
+            let result = calc_gauge(gauge, &all_pgreat);
+            assert!(result.cleared);
+            assert_eq!(result.value, 100.0);
+        }
+
+        let poors = Judgements::Summary([0, 0, 0, 0, 10])
+            .into_sequence(1000)
+            .unwrap();
+        let ex_hard = calc_gauge(Gauge::ExHard, &poors);
+        assert!(!ex_hard.cleared);
+        assert!(ex_hard.failed_at.is_some());
+        assert!(calc_gauge(Gauge::Hard, &poors).cleared);
+    }
+
+    #[test]
+    fn test_into_sequence() {
+        assert!(Judgements::Summary([10, 0, 0, 0, 0])
+            .into_sequence(5)
+            .is_none());
+    }
+}
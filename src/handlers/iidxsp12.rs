@@ -1,35 +1,9 @@
-use fuzzy_matcher::{clangd::ClangdMatcher, FuzzyMatcher};
-use priority_queue::PriorityQueue;
 use reqwest::get;
 use serde::{Deserialize, Serialize};
-use std::{error::Error, fmt, hash::Hash};
+use std::{error::Error, hash::Hash};
 use teloxide::{prelude::*, types::ReplyParameters};
 
-#[derive(PartialEq, Eq, Hash, Debug, Deserialize, Serialize)]
-pub enum Difficulty {
-    #[serde(rename = "B")]
-    Beginner,
-    #[serde(rename = "N")]
-    Normal,
-    #[serde(rename = "H")]
-    Hyper,
-    #[serde(rename = "A")]
-    Another,
-    #[serde(rename = "L")]
-    Leggendaria,
-}
-
-impl fmt::Display for Difficulty {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Difficulty::Beginner => write!(f, "BEGINNER"),
-            Difficulty::Normal => write!(f, "NORMAL"),
-            Difficulty::Hyper => write!(f, "HYPER"),
-            Difficulty::Another => write!(f, "ANOTHER"),
-            Difficulty::Leggendaria => write!(f, "LEGGENDARIA"),
-        }
-    }
-}
+use crate::{arcana::iidx::Difficulty, fuzzy::best_matches};
 
 #[derive(PartialEq, Eq, Hash, Debug, Deserialize, Serialize)]
 struct Song {
@@ -40,21 +14,6 @@ struct Song {
     version: u8,
 }
 
-fn pop_same_priority<T: PartialEq + Eq + Hash>(pq: &mut PriorityQueue<T, i64>) -> Vec<T> {
-    let mut result = Vec::new();
-    let priority = pq.peek().map(|(_, p)| *p);
-    if let Some(priority) = priority {
-        while let Some((entry, p)) = pq.pop() {
-            if p == priority {
-                result.push(entry);
-            } else {
-                break;
-            }
-        }
-    }
-    result
-}
-
 pub async fn sp12(
     bot: Bot,
     message: Message,
@@ -65,16 +24,8 @@ pub async fn sp12(
         .json::<Vec<Song>>()
         .await?;
 
-    let matcher = ClangdMatcher::default();
-    let mut pq: PriorityQueue<&Song, i64> = PriorityQueue::new();
-    for entry in table.iter() {
-        if let Some(score) = matcher.fuzzy_match(&entry.name, title) {
-            pq.push(entry, score);
-        }
-    }
-
-    if !pq.is_empty() {
-        let entries = pop_same_priority(&mut pq);
+    let entries = best_matches(&table, title, |entry| &entry.name);
+    if !entries.is_empty() {
         for entry in entries {
             bot.send_message(
                 message.chat.id,
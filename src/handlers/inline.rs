@@ -0,0 +1,40 @@
+use std::error::Error;
+use teloxide::{
+    prelude::*,
+    types::{
+        InlineQueryResult, InlineQueryResultArticle, InputMessageContent, InputMessageContentText,
+    },
+};
+
+use crate::calculator;
+
+fn article(id: impl Into<String>, title: &str, text: String) -> InlineQueryResult {
+    InlineQueryResult::Article(
+        InlineQueryResultArticle::new(
+            id,
+            title,
+            InputMessageContent::Text(InputMessageContentText::new(text.clone())),
+        )
+        .description(text),
+    )
+}
+
+/// Answer inline queries like `@bot vf 18 9850000 comp` with calculator results
+pub async fn inline_query(
+    bot: Bot,
+    query: InlineQuery,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let text = query.query.trim();
+    let (name, input) = text.split_once(' ').unwrap_or((text, ""));
+    let results = match calculator::evaluate(name, input) {
+        Some(output) => vec![article(name, text, output)],
+        // list the available calculators
+        None => calculator::usages()
+            .enumerate()
+            .map(|(i, usage)| article(i.to_string(), usage, usage.to_owned()))
+            .collect(),
+    };
+    bot.answer_inline_query(query.id, results).await?;
+
+    Ok(())
+}
@@ -1,10 +1,13 @@
-use std::error::Error;
-use teloxide::{prelude::*, types::ReplyParameters};
+use teloxide::utils::command::ParseError;
 
-use crate::maimai_courses::{Status, Submission};
+use crate::{
+    calculator::ScoreCalculator,
+    commands::{next_str_into_u32, parse_score},
+    maimai_courses::{Status, Submission},
+};
 
 /// Calculate the life remains
-pub async fn calc_life(submission: &Submission) -> (u32, Status) {
+pub fn calc_life(submission: &Submission) -> (u32, Status) {
     let mut remains = submission.life;
     for result in submission.results.iter() {
         if let Some(val) = remains.checked_sub(
@@ -28,17 +31,41 @@ pub async fn calc_life(submission: &Submission) -> (u32, Status) {
     (remains, Status::Passed)
 }
 
-pub async fn calc(
-    bot: Bot,
-    message: Message,
-    submission: &Submission,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    let (remain, status) = calc_life(submission).await;
-    bot.send_message(
-        message.chat.id,
-        format!("Life: {}/{}\n{}", remain, submission.life, status),
-    )
-    .reply_parameters(ReplyParameters::new(message.id))
-    .await?;
-    Ok(())
+impl ScoreCalculator for Submission {
+    const NAME: &'static str = "calc";
+    const USAGE: &'static str = "/calc LIFE HEAL [(RULE: GREAT,GOOD,MISS)] [[GREAT,GOOD,MISS]..]";
+
+    type Output = (u32, Status);
+
+    /// Parse a score calc command
+    fn parse(input: &str) -> Result<Self, ParseError> {
+        // The command should satisfy this pattern:
+        // /command LIFE HEAL [[GREAT,GOOD,MISS]..]
+        //
+        // For example:
+        // /calc 500 30 10,3,1 13,2,0 3,0,0 0,0,0
+        let mut parts = input.split_whitespace();
+        let marker = next_str_into_u32(parts.next())?;
+        let heal = next_str_into_u32(parts.next())?;
+        let mut results = parse_score(parts)?;
+        let mut rule = [2, 3, 5];
+        if results.len() == 4 {
+            rule = results.pop_front().unwrap();
+        }
+
+        Ok(Submission {
+            life: marker,
+            heal,
+            rule,
+            results,
+        })
+    }
+
+    fn compute(&self) -> Self::Output {
+        calc_life(self)
+    }
+
+    fn render(&self, (remain, status): &Self::Output) -> String {
+        format!("Life: {}/{}\n{}", remain, self.life, status)
+    }
 }
@@ -27,7 +27,8 @@ pub async fn score(
     }
     // get user id
     let user = message
-        .from()
+        .from
+        .as_ref()
         .ok_or_else(|| ParseError::Custom("invalid user".into()))?
         .id
         .0;
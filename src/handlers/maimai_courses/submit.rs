@@ -5,7 +5,7 @@ use teloxide::{
     utils::{command::ParseError, markdown::*},
 };
 
-use super::calc::calc_life;
+use super::calc_life;
 use crate::{
     commands::Results,
     maimai_courses::{
@@ -33,7 +33,8 @@ pub async fn submit(
     }
     // get user id
     let user = message
-        .from()
+        .from
+        .as_ref()
         .ok_or_else(|| ParseError::Custom("invalid user".into()))?;
     let course = &courses.get(level as usize - 1).unwrap();
     let life = course.life;
@@ -42,8 +43,7 @@ pub async fn submit(
         heal: course.heal,
         rule: RULE,
         results,
-    })
-    .await;
+    });
 
     records
         .entry(user.id.0)
@@ -1,4 +1,12 @@
+pub mod admin;
 pub mod arcana;
+pub mod bpi;
+pub mod calculator;
+pub mod callback;
 pub mod chuni_tolerance_calc;
+pub mod ddr_score_calc;
+pub mod iidx_gauge_calc;
 pub mod iidxsp12;
+pub mod inline;
 pub mod maimai_courses;
+pub mod sdvx;
@@ -0,0 +1,138 @@
+use std::{fmt, str::FromStr};
+use teloxide::utils::command::ParseError;
+
+use super::MAX_SCORE;
+use crate::{calculator::ScoreCalculator, commands::next_str_into_u32};
+
+/// SOUND VOLTEX score grades
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Grade {
+    D,
+    C,
+    B,
+    A,
+    APlus,
+    Aa,
+    AaPlus,
+    Aaa,
+    AaaPlus,
+    S,
+}
+
+pub const GRADES: [Grade; 10] = [
+    Grade::S,
+    Grade::AaaPlus,
+    Grade::Aaa,
+    Grade::AaPlus,
+    Grade::Aa,
+    Grade::APlus,
+    Grade::A,
+    Grade::B,
+    Grade::C,
+    Grade::D,
+];
+
+impl fmt::Display for Grade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Grade::D => write!(f, "D"),
+            Grade::C => write!(f, "C"),
+            Grade::B => write!(f, "B"),
+            Grade::A => write!(f, "A"),
+            Grade::APlus => write!(f, "A+"),
+            Grade::Aa => write!(f, "AA"),
+            Grade::AaPlus => write!(f, "AA+"),
+            Grade::Aaa => write!(f, "AAA"),
+            Grade::AaaPlus => write!(f, "AAA+"),
+            Grade::S => write!(f, "S"),
+        }
+    }
+}
+
+impl FromStr for Grade {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        GRADES
+            .iter()
+            .find(|g| g.to_string().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| format!("invalid grade: {}", s))
+    }
+}
+
+impl Grade {
+    /// Minimum score of the grade
+    pub fn border(&self) -> u32 {
+        match self {
+            Grade::D => 0,
+            Grade::C => 6_500_000,
+            Grade::B => 7_500_000,
+            Grade::A => 8_700_000,
+            Grade::APlus => 9_000_000,
+            Grade::Aa => 9_300_000,
+            Grade::AaPlus => 9_500_000,
+            Grade::Aaa => 9_700_000,
+            Grade::AaaPlus => 9_800_000,
+            Grade::S => 9_900_000,
+        }
+    }
+
+    /// Volforce coefficient of the grade, in hundredths
+    pub fn coef(&self) -> u64 {
+        match self {
+            Grade::D => 80,
+            Grade::C => 82,
+            Grade::B => 85,
+            Grade::A => 88,
+            Grade::APlus => 91,
+            Grade::Aa => 94,
+            Grade::AaPlus => 97,
+            Grade::Aaa => 100,
+            Grade::AaaPlus => 102,
+            Grade::S => 105,
+        }
+    }
+
+    /// Get the grade of a score
+    pub fn of(score: u32) -> Self {
+        *GRADES.iter().find(|g| score >= g.border()).unwrap()
+    }
+}
+
+pub struct GradeCalc {
+    pub score: u32,
+}
+
+impl ScoreCalculator for GradeCalc {
+    const NAME: &'static str = "sdvxgrade";
+    const USAGE: &'static str = "/sdvxgrade SCORE";
+
+    type Output = Grade;
+
+    fn parse(input: &str) -> Result<Self, ParseError> {
+        let score = next_str_into_u32(input.split_whitespace().next())?;
+        if score > MAX_SCORE {
+            return Err(ParseError::Custom("invalid score".into()));
+        }
+
+        Ok(GradeCalc { score })
+    }
+
+    fn compute(&self) -> Self::Output {
+        Grade::of(self.score)
+    }
+
+    fn render(&self, grade: &Self::Output) -> String {
+        match GRADES.iter().rev().find(|g| *g > grade) {
+            Some(next) => format!(
+                "Grade: {}\n{} more to {} ({})",
+                grade,
+                next.border() - self.score,
+                next,
+                next.border()
+            ),
+            None => format!("Grade: {}", grade),
+        }
+    }
+}
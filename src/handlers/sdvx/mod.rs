@@ -0,0 +1,10 @@
+pub mod grade;
+pub mod tolerance;
+pub mod volforce;
+
+pub use grade::*;
+pub use tolerance::*;
+pub use volforce::*;
+
+/// Maximum score of a chart
+pub const MAX_SCORE: u32 = 10_000_000;
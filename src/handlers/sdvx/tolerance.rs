@@ -0,0 +1,65 @@
+use teloxide::utils::command::ParseError;
+
+use super::{Grade, MAX_SCORE};
+use crate::{calculator::ScoreCalculator, commands::next_str_into_u32};
+
+/// Maximum NEARs and ERRORs that keep the target score with the given chains
+pub fn calc_tolerance(chains: u32, target: u32) -> (u64, u64) {
+    // score = floor(10,000,000 * (CRITICAL * 2 + NEAR) / (chains * 2))
+    let loss = (MAX_SCORE - target) as u64 * chains as u64;
+    (loss * 2 / MAX_SCORE as u64, loss / MAX_SCORE as u64)
+}
+
+pub struct ToleranceCalc {
+    pub chains: u32,
+    pub target: u32,
+}
+
+impl ScoreCalculator for ToleranceCalc {
+    const NAME: &'static str = "sdvxtolerance";
+    const USAGE: &'static str = "/sdvxtolerance CHAINS TARGET";
+
+    type Output = (u64, u64);
+
+    fn parse(input: &str) -> Result<Self, ParseError> {
+        let mut parts = input.split_whitespace();
+        let chains = next_str_into_u32(parts.next())?;
+        let target = parts.next().unwrap_or("");
+        let target = target
+            .parse::<u32>()
+            .ok()
+            .or_else(|| target.parse::<Grade>().ok().map(|g| g.border()))
+            .ok_or_else(|| ParseError::Custom("invalid target".into()))?;
+        if chains == 0 || target > MAX_SCORE {
+            return Err(ParseError::Custom("invalid chains or target".into()));
+        }
+
+        Ok(ToleranceCalc { chains, target })
+    }
+
+    fn compute(&self) -> Self::Output {
+        calc_tolerance(self.chains, self.target)
+    }
+
+    fn render(&self, (nears, errors): &Self::Output) -> String {
+        format!(
+            "For target {} we can have {} NEAR(s) or {} ERROR(s)\nNEAR: -{:.2}, ERROR: -{:.2}",
+            self.target,
+            nears,
+            errors,
+            MAX_SCORE as f64 / self.chains as f64 / 2.0,
+            MAX_SCORE as f64 / self.chains as f64,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calc_tolerance() {
+        assert_eq!(calc_tolerance(2000, 9_900_000), (40, 20));
+        assert_eq!(calc_tolerance(1234, MAX_SCORE), (0, 0));
+    }
+}
@@ -0,0 +1,136 @@
+use std::{fmt, str::FromStr};
+use teloxide::utils::command::ParseError;
+
+use super::{Grade, MAX_SCORE};
+use crate::{calculator::ScoreCalculator, commands::next_str_into_u32};
+
+/// SOUND VOLTEX clear medals
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClearMedal {
+    Played,
+    Complete,
+    ExcessiveComplete,
+    MaxxiveComplete,
+    UltimateChain,
+    PerfectUltimateChain,
+}
+
+impl fmt::Display for ClearMedal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClearMedal::Played => write!(f, "PLAYED"),
+            ClearMedal::Complete => write!(f, "COMPLETE"),
+            ClearMedal::ExcessiveComplete => write!(f, "EXCESSIVE COMPLETE"),
+            ClearMedal::MaxxiveComplete => write!(f, "MAXXIVE COMPLETE"),
+            ClearMedal::UltimateChain => write!(f, "ULTIMATE CHAIN"),
+            ClearMedal::PerfectUltimateChain => write!(f, "PERFECT ULTIMATE CHAIN"),
+        }
+    }
+}
+
+impl FromStr for ClearMedal {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "played" | "failed" | "crash" => Ok(ClearMedal::Played),
+            "comp" | "complete" | "clear" | "effective" => Ok(ClearMedal::Complete),
+            "exc" | "excessive" | "hard" => Ok(ClearMedal::ExcessiveComplete),
+            "mxv" | "maxxive" => Ok(ClearMedal::MaxxiveComplete),
+            "uc" => Ok(ClearMedal::UltimateChain),
+            "puc" => Ok(ClearMedal::PerfectUltimateChain),
+            _ => Err(format!("invalid clear medal: {}", s)),
+        }
+    }
+}
+
+impl ClearMedal {
+    /// Volforce coefficient of the clear medal, in hundredths
+    pub fn coef(&self) -> u64 {
+        match self {
+            ClearMedal::Played => 50,
+            ClearMedal::Complete => 100,
+            ClearMedal::ExcessiveComplete => 102,
+            ClearMedal::MaxxiveComplete => 104,
+            ClearMedal::UltimateChain => 105,
+            ClearMedal::PerfectUltimateChain => 110,
+        }
+    }
+}
+
+/// Calculate the single chart Volforce, in thousandths
+pub fn calc_volforce(level: u32, score: u32, medal: ClearMedal) -> u64 {
+    // level * (score / 10,000,000) * grade * medal * 20, truncated to 3 decimals
+    level as u64 * score as u64 * Grade::of(score).coef() * medal.coef() * 20
+        / (MAX_SCORE as u64 * 10_000)
+}
+
+pub struct VolforceCalc {
+    pub level: u32,
+    pub score: u32,
+    pub medal: ClearMedal,
+}
+
+impl ScoreCalculator for VolforceCalc {
+    const NAME: &'static str = "vf";
+    const USAGE: &'static str = "/vf LEVEL SCORE PLAYED/COMP/EXC/MXV/UC/PUC";
+
+    type Output = u64;
+
+    fn parse(input: &str) -> Result<Self, ParseError> {
+        let mut parts = input.split_whitespace();
+        let level = next_str_into_u32(parts.next())?;
+        let score = next_str_into_u32(parts.next())?;
+        let medal = parts
+            .next()
+            .unwrap_or("")
+            .parse::<ClearMedal>()
+            .map_err(|e| ParseError::Custom(e.into()))?;
+        if !(1..=20).contains(&level) || score > MAX_SCORE {
+            return Err(ParseError::Custom("invalid level or score".into()));
+        }
+
+        Ok(VolforceCalc {
+            level,
+            score,
+            medal,
+        })
+    }
+
+    fn compute(&self) -> Self::Output {
+        calc_volforce(self.level, self.score, self.medal)
+    }
+
+    fn render(&self, vf: &Self::Output) -> String {
+        format!(
+            "Lv.{} {} {} {}\nVolforce: {}.{:03}",
+            self.level,
+            self.score,
+            Grade::of(self.score),
+            self.medal,
+            vf / 1000,
+            vf % 1000
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calc_volforce() {
+        assert_eq!(
+            calc_volforce(20, MAX_SCORE, ClearMedal::PerfectUltimateChain),
+            462
+        );
+        assert_eq!(calc_volforce(18, 9_850_000, ClearMedal::Complete), 361);
+        assert_eq!(calc_volforce(17, 9_000_000, ClearMedal::Played), 139);
+        assert_eq!(
+            calc_volforce(18, 9_850_000, ClearMedal::MaxxiveComplete),
+            376
+        );
+        assert_eq!("maxxive".parse(), Ok(ClearMedal::MaxxiveComplete));
+        assert_eq!("exc".parse(), Ok(ClearMedal::ExcessiveComplete));
+    }
+}
@@ -0,0 +1,340 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    sync::Arc,
+};
+use teloxide::types::{ChatId, UserId};
+use tokio::sync::RwLock;
+
+use crate::{progress::Snapshot, store::JsonFile};
+
+/// Arcana IIDX profile linked to a Telegram user
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Link {
+    /// Preferred game version, where the profile ID belongs to
+    pub version: u32,
+    pub profile_id: String,
+    pub iidx_id: String,
+    pub dj_name: String,
+    /// Where to post new personal bests, if opted in
+    #[serde(default)]
+    pub notify: Option<Subscription>,
+    /// Snapshots of the profile stats whenever they changed, oldest first
+    #[serde(default)]
+    pub history: Vec<Snapshot>,
+    /// Positions on group boards the last time they were shown, by board
+    #[serde(default)]
+    pub positions: HashMap<String, usize>,
+    /// Chats the user has used the bot in, where they show up on boards
+    #[serde(default)]
+    pub chats: HashSet<ChatId>,
+}
+
+/// Chat to post new personal bests of a linked profile in
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Subscription {
+    pub chat_id: ChatId,
+    /// Timestamp of the latest play checked, so that no play is posted twice
+    pub last_seen: String,
+}
+
+/// Push the snapshot unless the stats are unchanged, returning whether it is
+/// pushed
+fn push_changed(history: &mut Vec<Snapshot>, snapshot: Snapshot) -> bool {
+    if history
+        .last()
+        .is_some_and(|last| last.same_stats(&snapshot))
+    {
+        return false;
+    }
+    history.push(snapshot);
+    true
+}
+
+/// Telegram user to Arcana profile links persisted to disk
+#[derive(Debug, Clone)]
+pub struct Links {
+    links: Arc<RwLock<HashMap<u64, Link>>>,
+    file: JsonFile,
+}
+
+impl Links {
+    /// Load the links from the path, starting empty if it does not exist
+    pub async fn load(path: impl AsRef<Path>) -> Self {
+        let file = JsonFile::new(path);
+        Self {
+            links: Arc::new(RwLock::new(file.load().await)),
+            file,
+        }
+    }
+
+    async fn save(&self) {
+        if let Err(e) = self.file.save(&self.links).await {
+            log::warn!("Failed to save links: {}", e);
+        }
+    }
+
+    pub async fn get(&self, user: UserId) -> Option<Link> {
+        self.links.read().await.get(&user.0).cloned()
+    }
+
+    /// Link the user to a profile, keeping the chats, and the history, board
+    /// positions and notifications if it is the same player as before
+    pub async fn set(&self, user: UserId, mut link: Link) {
+        let mut links = self.links.write().await;
+        if let Some(old) = links.remove(&user.0) {
+            link.chats.extend(old.chats);
+            if old.iidx_id == link.iidx_id {
+                for snapshot in std::mem::replace(&mut link.history, old.history) {
+                    push_changed(&mut link.history, snapshot);
+                }
+                link.positions = old.positions;
+                link.notify = link.notify.or(old.notify);
+            }
+        }
+        links.insert(user.0, link);
+        drop(links);
+        self.save().await;
+    }
+
+    pub async fn all(&self) -> Vec<(UserId, Link)> {
+        self.links
+            .read()
+            .await
+            .iter()
+            .map(|(user, link)| (UserId(*user), link.clone()))
+            .collect()
+    }
+
+    /// Remember that the linked user has used the bot in the chat
+    pub async fn visit(&self, user: UserId, chat_id: ChatId) {
+        let mut links = self.links.write().await;
+        let Some(link) = links.get_mut(&user.0) else {
+            return;
+        };
+        if link.chats.insert(chat_id) {
+            drop(links);
+            self.save().await;
+        }
+    }
+
+    /// Linked users who have used the bot in the chat
+    pub async fn in_chat(&self, chat_id: ChatId) -> Vec<(UserId, Link)> {
+        self.links
+            .read()
+            .await
+            .iter()
+            .filter(|(_, link)| link.chats.contains(&chat_id))
+            .map(|(user, link)| (UserId(*user), link.clone()))
+            .collect()
+    }
+
+    /// Add the snapshot to the history of the user if the stats changed
+    pub async fn record(&self, user: UserId, snapshot: Snapshot) {
+        let mut links = self.links.write().await;
+        let Some(link) = links.get_mut(&user.0) else {
+            return;
+        };
+        if push_changed(&mut link.history, snapshot) {
+            drop(links);
+            self.save().await;
+        }
+    }
+
+    /// History of the player with the IIDX ID, if linked by anyone
+    pub async fn history(&self, iidx_id: &str) -> Option<Vec<Snapshot>> {
+        self.links
+            .read()
+            .await
+            .values()
+            .find(|link| link.iidx_id == iidx_id)
+            .map(|link| link.history.clone())
+    }
+
+    /// Record the positions of the users on the board, returning where they
+    /// were the last time it was shown
+    pub async fn reposition(
+        &self,
+        board: &str,
+        positions: &[(UserId, usize)],
+    ) -> Vec<Option<usize>> {
+        let mut links = self.links.write().await;
+        let previous = positions
+            .iter()
+            .map(|(user, position)| {
+                links
+                    .get_mut(&user.0)
+                    .and_then(|link| link.positions.insert(board.to_owned(), *position))
+            })
+            .collect();
+        drop(links);
+        self.save().await;
+        previous
+    }
+
+    /// All links with personal best notifications on
+    pub async fn subscribed(&self) -> Vec<(UserId, Link)> {
+        self.links
+            .read()
+            .await
+            .iter()
+            .filter(|(_, link)| link.notify.is_some())
+            .map(|(user, link)| (UserId(*user), link.clone()))
+            .collect()
+    }
+
+    /// Post new personal bests of the linked profile from now on in the chat,
+    /// returning whether the user is linked
+    pub async fn subscribe(&self, user: UserId, chat_id: ChatId) -> bool {
+        let subscribed = match self.links.write().await.get_mut(&user.0) {
+            Some(link) => {
+                link.notify = Some(Subscription {
+                    chat_id,
+                    last_seen: Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+                });
+                true
+            }
+            None => false,
+        };
+        self.save().await;
+        subscribed
+    }
+
+    /// Stop posting personal bests, returning whether they have been posted
+    pub async fn unsubscribe(&self, user: UserId) -> bool {
+        let unsubscribed = self
+            .links
+            .write()
+            .await
+            .get_mut(&user.0)
+            .and_then(|link| link.notify.take())
+            .is_some();
+        self.save().await;
+        unsubscribed
+    }
+
+    /// Mark plays of the user up to the timestamp as checked
+    pub async fn seen(&self, user: UserId, timestamp: &str) {
+        if let Some(notify) = self
+            .links
+            .write()
+            .await
+            .get_mut(&user.0)
+            .and_then(|link| link.notify.as_mut())
+        {
+            notify.last_seen = timestamp.to_owned();
+        }
+        self.save().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arcana::iidx::Score;
+
+    #[tokio::test]
+    async fn test_links() {
+        let path = std::env::temp_dir().join(format!("links-{}.json", std::process::id()));
+        let link = Link {
+            version: 28,
+            profile_id: "C3PttzgAx6F".to_owned(),
+            iidx_id: "1015-0869".to_owned(),
+            dj_name: "ORIGIN".to_owned(),
+            notify: None,
+            history: Vec::new(),
+            positions: HashMap::new(),
+            chats: HashSet::new(),
+        };
+
+        let links = Links::load(&path).await;
+        assert_eq!(links.get(UserId(42)).await, None);
+        links.set(UserId(42), link.clone()).await;
+
+        // survives a restart
+        let links = Links::load(&path).await;
+        assert_eq!(links.get(UserId(42)).await, Some(link.clone()));
+
+        assert!(!links.subscribe(UserId(7), ChatId(-100)).await);
+        assert!(links.subscribe(UserId(42), ChatId(-100)).await);
+        links.seen(UserId(42), "2021-10-03T13:01:52Z").await;
+        let subscribed = Links::load(&path).await.subscribed().await;
+        assert_eq!(subscribed.len(), 1);
+        assert_eq!(
+            subscribed[0].1.notify,
+            Some(Subscription {
+                chat_id: ChatId(-100),
+                last_seen: "2021-10-03T13:01:52Z".to_owned(),
+            })
+        );
+        links.visit(UserId(7), ChatId(-100)).await;
+        links.visit(UserId(42), ChatId(-100)).await;
+        let in_chat = Links::load(&path).await.in_chat(ChatId(-100)).await;
+        assert_eq!(
+            in_chat.iter().map(|(user, _)| *user).collect::<Vec<_>>(),
+            vec![UserId(42)]
+        );
+        assert!(links.in_chat(ChatId(-200)).await.is_empty());
+        // relinking the same player keeps the notifications
+        links.set(UserId(42), link.clone()).await;
+        assert!(links.get(UserId(42)).await.unwrap().notify.is_some());
+        // relinking keeps the chats
+        assert_eq!(
+            links.get(UserId(42)).await.unwrap().chats,
+            HashSet::from([ChatId(-100)])
+        );
+        assert!(links.unsubscribe(UserId(42)).await);
+        assert!(!links.unsubscribe(UserId(42)).await);
+
+        let snapshot = |date: &str, dj_points| Snapshot {
+            date: date.to_owned(),
+            version: 28,
+            sp: Score {
+                dj_points,
+                plays: 842,
+                rank: None,
+            },
+            dp: Score {
+                dj_points: 0,
+                plays: 3,
+                rank: None,
+            },
+        };
+        links.record(UserId(42), snapshot("2021-10-01", 1500)).await;
+        links.record(UserId(42), snapshot("2021-10-02", 1500)).await;
+        links.record(UserId(42), snapshot("2021-10-03", 1532)).await;
+        // relinking the same player keeps the history
+        links
+            .set(
+                UserId(42),
+                Link {
+                    history: vec![snapshot("2021-10-04", 1532)],
+                    ..link
+                },
+            )
+            .await;
+        let history = links.history("1015-0869").await.unwrap();
+        assert_eq!(
+            history.iter().map(|s| s.date.as_str()).collect::<Vec<_>>(),
+            vec!["2021-10-01", "2021-10-03"]
+        );
+
+        let board = "-100:SP:dj_points";
+        assert_eq!(
+            links
+                .reposition(board, &[(UserId(42), 2), (UserId(7), 1)])
+                .await,
+            vec![None, None]
+        );
+        assert_eq!(
+            Links::load(&path)
+                .await
+                .reposition(board, &[(UserId(42), 1)])
+                .await,
+            vec![Some(2)]
+        );
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+}
@@ -3,31 +3,70 @@
 use commands::Command;
 use lazy_static::lazy_static;
 use lisp_rs::lisp_rs_eval;
-use std::error::Error;
+use std::{error::Error, sync::Arc, time::Duration};
 use teloxide::{filter_command, prelude::*, types::ReplyParameters, utils::command::BotCommands};
-use tokio::fs;
+use tokio::{fs, sync::RwLock};
 
 mod arcana;
+mod bpi;
+mod calculator;
 mod commands;
+mod fuzzy;
 mod handlers;
+mod links;
 mod macros;
 mod maimai_courses;
+mod notifier;
+mod progress;
+mod store;
 
-use maimai_courses::{Courses, Records};
+use arcana::{iidx::MetadataCache, ArcanaClient, Game};
+use bpi::BpiTable;
+use handlers::{
+    arcana::ArcanaContext,
+    chuni_tolerance_calc::ChuniTolerance,
+    ddr_score_calc::DdrScore,
+    iidx_gauge_calc::GaugeCalc,
+    sdvx::{GradeCalc, ToleranceCalc, VolforceCalc},
+};
+use links::Links;
+use maimai_courses::{Courses, Records, Submission};
 
 const ABOUT: &str =
     "Arcade MUG Bot, designed by OriginCode.\nGitHub: https://github.com/OriginCode/arcmugbot";
 const TOKEN: &str = "";
+const ARCANA_URL: &str = "https://arcana.nu/api/v1/";
 const ARCANA_TOKEN: &str = "";
+const ARCANA_CACHE_PATH: &str = "./arcana-cache.json";
+/// How long music and chart metadata are cached
+const ARCANA_CACHE_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+/// IIDX version used when neither given nor linked, unless overridden by
+/// the `IIDX_VERSION` environment variable
+const FALLBACK_IIDX_VERSION: u32 = 28;
+const LINKS_PATH: &str = "./links.json";
+/// How often linked profiles are polled for new personal bests
+const NOTIFY_INTERVAL: Duration = Duration::from_secs(10 * 60);
+/// How often snapshots of the linked profiles are taken
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+/// Telegram user IDs allowed to run admin commands
+const ADMINS: &[u64] = &[];
+const BPI_PATH: &str = "./bpi.json";
 // const TZ: Tz = chrono_tz::Asia::Shanghai;
 
 lazy_static! {
     pub static ref DATE: String = format!("{}-{}", 2022, 1);
+    pub static ref DEFAULT_IIDX_VERSION: u32 = std::env::var("IIDX_VERSION")
+        .ok()
+        .and_then(|v| Game::Iidx.parse_version(&v))
+        .unwrap_or(FALLBACK_IIDX_VERSION);
 }
 
 fn lisp_eval(input: String) -> String {
     let (tx, rx) = std::sync::mpsc::channel();
-    let t = std::thread::spawn(move || tx.send(lisp_rs_eval(&input)).unwrap());
+    let t = std::thread::spawn(move || {
+        tx.send(calculator::lisp_call(&input).unwrap_or_else(|| lisp_rs_eval(&input)))
+            .unwrap()
+    });
     let ret = rx
         .recv_timeout(std::time::Duration::from_secs(5))
         .unwrap_or("timeout".to_owned());
@@ -43,7 +82,13 @@ async fn answer(
     command: Command,
     mut records: Records,
     courses: Courses,
+    bpi_table: BpiTable,
+    arcana: ArcanaContext,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
+    // linked users show up on the boards of the chats they use the bot in
+    if let Some(user) = &message.from {
+        arcana.links.visit(user.id, message.chat.id).await;
+    }
     match command {
         Command::Ping => {
             bot.send_message(message.chat.id, "pong!").await?;
@@ -56,11 +101,8 @@ async fn answer(
         Command::About => {
             bot.send_message(message.chat.id, ABOUT).await?;
         }
-        Command::Calc { submission } => {
-            handlers::maimai_courses::calc(bot, message, &submission).await?
-        }
-        Command::CalcCustom { submission } => {
-            handlers::maimai_courses::calc(bot, message, &submission).await?
+        Command::Calc { input } | Command::CalcCustom { input } => {
+            handlers::calculator::calculate::<Submission>(bot, message, &input).await?
         }
         Command::Submit { level, results } => {
             handlers::maimai_courses::submit(bot, message, level, results, &courses, &mut records)
@@ -78,17 +120,131 @@ async fn answer(
         Command::Rank { level } => {
             handlers::maimai_courses::rank(bot, message, level, &courses, &mut records).await?
         }
-        Command::IIDXProfile { version, param } => {
-            handlers::arcana::iidx::profile(bot, message, version, &param).await?
+        Command::IIDXProfile { player } => {
+            handlers::arcana::iidx::profile(bot, message, &arcana, player).await?
+        }
+        Command::IIDXProgress { player } => {
+            handlers::arcana::iidx::progress(bot, message, &arcana, player).await?
         }
         Command::IIDXMusic { version, title } => {
-            handlers::arcana::iidx::music(bot, message, version, &title).await?
+            handlers::arcana::iidx::music(bot, message, &arcana, version, &title).await?
+        }
+        Command::IIDXRecent { player, options } => {
+            handlers::arcana::iidx::recent(bot, message, &arcana, player, options).await?
+        }
+        Command::IIDXBest { player, title } => {
+            handlers::arcana::iidx::best(bot, message, &arcana, player, &title).await?
+        }
+        Command::IIDXLamps {
+            player,
+            level,
+            play_style,
+        } => {
+            handlers::arcana::iidx::lamps(bot, message, &arcana, player, level, play_style).await?
+        }
+        Command::IIDXVs {
+            version,
+            player_a,
+            player_b,
+            level,
+        } => {
+            handlers::arcana::iidx::vs(bot, message, &arcana, version, &player_a, &player_b, level)
+                .await?
+        }
+        Command::IIDXBoard { options } => {
+            handlers::arcana::iidx::board(bot, message, &arcana, options).await?
+        }
+        Command::IIDXLink { param, version } => {
+            handlers::arcana::iidx::link(bot, message, &arcana, &param, version).await?
+        }
+        Command::IIDXNotify { action } => {
+            handlers::arcana::iidx::notify(bot, message, &arcana.links, &action).await?
+        }
+        Command::IIDXCache { action } => {
+            handlers::arcana::iidx::cache(bot, message, &arcana, &action).await?
+        }
+        Command::ChuniTolerance { input } => {
+            handlers::calculator::calculate::<ChuniTolerance>(bot, message, &input).await?
+        }
+        Command::Versions => handlers::arcana::versions::versions(bot, message).await?,
+        Command::SdvxProfile { player } => {
+            handlers::arcana::profile(
+                bot,
+                message,
+                &arcana.client,
+                player,
+                handlers::arcana::sdvx::render_profile,
+            )
+            .await?
         }
-        Command::IIDXRecent { version, param } => {
-            handlers::arcana::iidx::recent(bot, message, version, &param).await?
+        Command::SdvxMusic { version, title } => {
+            handlers::arcana::music(
+                bot,
+                message,
+                &arcana.client,
+                version,
+                &title,
+                handlers::arcana::sdvx::render_music,
+            )
+            .await?
         }
-        Command::ChuniTolerance { notes, target } => {
-            handlers::chuni_tolerance_calc::tolerance_calc(bot, message, notes, &target).await?
+        Command::SdvxRecent { player, n } => {
+            handlers::arcana::recent::<arcana::sdvx::Profile, _, _>(
+                bot,
+                message,
+                &arcana.client,
+                player,
+                n,
+                handlers::arcana::sdvx::render_play,
+            )
+            .await?
+        }
+        Command::DdrProfile { player } => {
+            handlers::arcana::profile(
+                bot,
+                message,
+                &arcana.client,
+                player,
+                handlers::arcana::ddr::render_profile,
+            )
+            .await?
+        }
+        Command::DdrMusic { version, title } => {
+            handlers::arcana::music(
+                bot,
+                message,
+                &arcana.client,
+                version,
+                &title,
+                handlers::arcana::ddr::render_music,
+            )
+            .await?
+        }
+        Command::DdrRecent { player, n } => {
+            handlers::arcana::recent::<arcana::ddr::Profile, _, _>(
+                bot,
+                message,
+                &arcana.client,
+                player,
+                n,
+                handlers::arcana::ddr::render_play,
+            )
+            .await?
+        }
+        Command::IIDXGauge { input } => {
+            handlers::calculator::calculate::<GaugeCalc>(bot, message, &input).await?
+        }
+        Command::Vf { input } => {
+            handlers::calculator::calculate::<VolforceCalc>(bot, message, &input).await?
+        }
+        Command::SdvxGrade { input } => {
+            handlers::calculator::calculate::<GradeCalc>(bot, message, &input).await?
+        }
+        Command::SdvxTolerance { input } => {
+            handlers::calculator::calculate::<ToleranceCalc>(bot, message, &input).await?
+        }
+        Command::DdrScore { input } => {
+            handlers::calculator::calculate::<DdrScore>(bot, message, &input).await?
         }
         Command::Lisp { input } => {
             bot.send_message(message.chat.id, lisp_eval(input))
@@ -96,7 +252,20 @@ async fn answer(
                 .await?;
         }
         Command::SP12 { title } => handlers::iidxsp12::sp12(bot, message, &title).await?,
+        Command::Bpi {
+            title,
+            difficulty,
+            ex_score,
+        } => handlers::bpi::bpi(bot, message, &title, difficulty, ex_score, &bpi_table).await?,
+        Command::BpiTotal { player } => {
+            handlers::arcana::iidx::bpi_total(bot, message, &arcana, &bpi_table, player).await?
+        }
+        Command::BpiImport { url } => {
+            handlers::bpi::bpi_import(bot, message, &url, &bpi_table).await?
+        }
     };
+    // save whatever the command fetched in one go
+    arcana.cache.flush().await;
 
     Ok(())
 }
@@ -105,6 +274,10 @@ async fn answer(
 async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
     pretty_env_logger::init();
     log::info!("Starting arcmugbot...");
+    log::info!(
+        "Default IIDX version: {}",
+        Game::Iidx.version_name(*DEFAULT_IIDX_VERSION)
+    );
 
     let bot = Bot::new(TOKEN);
 
@@ -113,12 +286,39 @@ async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
         serde_json::from_slice(&fs::read(format!("./records-{}.json", *DATE)).await?)?;
     let courses: Courses =
         serde_json::from_slice(&fs::read(format!("./courses-{}.json", *DATE)).await?)?;
+    let arcana = ArcanaContext {
+        client: ArcanaClient::new(ARCANA_URL, ARCANA_TOKEN)?,
+        cache: MetadataCache::load(ARCANA_CACHE_PATH, ARCANA_CACHE_TTL).await,
+        links: Links::load(LINKS_PATH).await,
+    };
+    let bpi_table: BpiTable = Arc::new(RwLock::new(match fs::read(BPI_PATH).await {
+        Ok(data) => serde_json::from_slice(&data)?,
+        Err(_) => Vec::new(),
+    }));
+
+    tokio::spawn(progress::run(
+        arcana.client.clone(),
+        arcana.links.clone(),
+        SNAPSHOT_INTERVAL,
+    ));
+    tokio::spawn(notifier::run(
+        bot.clone(),
+        arcana.client.clone(),
+        arcana.cache.clone(),
+        arcana.links.clone(),
+        NOTIFY_INTERVAL,
+    ));
 
     Dispatcher::builder(
         bot,
-        Update::filter_message().branch(filter_command::<Command, _>().endpoint(answer)),
+        dptree::entry()
+            .branch(
+                Update::filter_message().branch(filter_command::<Command, _>().endpoint(answer)),
+            )
+            .branch(Update::filter_inline_query().endpoint(handlers::inline::inline_query))
+            .branch(Update::filter_callback_query().endpoint(handlers::callback::callback)),
     )
-    .dependencies(dptree::deps![records, courses])
+    .dependencies(dptree::deps![records, courses, bpi_table, arcana])
     .enable_ctrlc_handler()
     .build()
     .dispatch()
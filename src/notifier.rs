@@ -0,0 +1,301 @@
+use std::{collections::HashMap, time::Duration};
+use teloxide::{
+    prelude::*,
+    types::{ParseMode, UserId},
+    utils::markdown::*,
+    ApiError, RequestError,
+};
+use tokio::time;
+
+use crate::{
+    arcana::{
+        iidx::{
+            best_by_chart, plays, query_score_history, Best, Lamp, MetadataCache, ScoreHistory,
+        },
+        ArcanaClient, Result,
+    },
+    links::{Link, Links, Subscription},
+};
+
+/// Plays checked per user and poll at most, the rest waits for the next poll
+const MAX_PLAYS_PER_POLL: u32 = 50;
+/// Delay between sending notifications, to stay under Telegram rate limits
+const SEND_DELAY: Duration = Duration::from_secs(3);
+/// Delay between fetching the history of each song, to spread the requests
+/// to Arcana
+const REQUEST_DELAY: Duration = Duration::from_secs(1);
+/// Times a notification is sent again after Telegram flood control, before
+/// leaving it for the next poll
+const MAX_SEND_RETRIES: u32 = 3;
+
+/// Whether the play beats the previous best on the chart by EX score or
+/// lamp, or is marked as raised by Arcana
+fn is_personal_best(play: &ScoreHistory, previous: Option<&Best>) -> bool {
+    play.raised
+        || match previous {
+            Some(best) => play.ex_score > best.ex_score.value || play.lamp > best.lamp.value,
+            None => play.lamp > Lamp::Failed,
+        }
+}
+
+fn render_personal_best(
+    dj_name: &str,
+    title: &str,
+    chart: &str,
+    play: &ScoreHistory,
+    previous: Option<&Best>,
+) -> String {
+    format!(
+        "{} set a new personal best\\!\n{}\n{}",
+        bold(&escape(dj_name)),
+        bold(&escape(title)),
+        escape(&format!(
+            "{}\nEX Score: {} → {}\nLamp: {} → {}",
+            chart,
+            previous.map_or("-".to_owned(), |b| b.ex_score.value.to_string()),
+            play.ex_score,
+            previous.map_or("-".to_owned(), |b| b.lamp.value.to_string()),
+            play.lamp,
+        ))
+    )
+}
+
+/// Whether posting in the chat can never succeed again, e.g. the bot is
+/// blocked by the user or removed from the group
+fn is_unreachable(error: &RequestError) -> bool {
+    match error {
+        RequestError::Api(
+            ApiError::BotBlocked
+            | ApiError::BotKicked
+            | ApiError::BotKickedFromSupergroup
+            | ApiError::UserDeactivated
+            | ApiError::ChatNotFound,
+        ) => true,
+        RequestError::Api(ApiError::Unknown(description)) => description.starts_with("Forbidden"),
+        _ => false,
+    }
+}
+
+/// Render the personal best of the play with the song title and chart
+async fn personal_best_message(
+    client: &ArcanaClient,
+    cache: &MetadataCache,
+    version: u32,
+    dj_name: &str,
+    play: &ScoreHistory,
+    previous: Option<&Best>,
+) -> Result<String> {
+    let title = cache
+        .music(client, version, &play.music_id)
+        .await?
+        .map_or(play.music_id.clone(), |m| m.title);
+    let chart = cache
+        .charts(client, version, &play.music_id)
+        .await?
+        .into_iter()
+        .find(|c| c.id == play.chart_id)
+        .map_or(String::new(), |c| {
+            format!("{} {} {}", c.play_style, c.difficulty, c.rating)
+        });
+    Ok(render_personal_best(
+        dj_name, &title, &chart, play, previous,
+    ))
+}
+
+/// Notify new personal bests of a subscribed user, returning the timestamp of
+/// the latest play checked
+async fn notify(
+    bot: &Bot,
+    client: &ArcanaClient,
+    cache: &MetadataCache,
+    links: &Links,
+    user: UserId,
+    link: &Link,
+    subscription: &Subscription,
+) -> Result<Option<String>> {
+    let Link {
+        version,
+        profile_id,
+        dj_name,
+        ..
+    } = link;
+    let new_plays: Vec<ScoreHistory> = query_score_history(
+        client,
+        *version,
+        &plays(profile_id)
+            .gt("timestamp", subscription.last_seen.as_str())
+            .sort("timestamp")
+            .max_results(MAX_PLAYS_PER_POLL),
+        Some(MAX_PLAYS_PER_POLL as usize),
+    )
+    .await?;
+
+    // earlier plays of each song played, fetched once for all its new plays
+    let mut earlier: HashMap<&str, Vec<ScoreHistory>> = HashMap::new();
+    for play in new_plays.iter() {
+        if earlier.contains_key(play.music_id.as_str()) {
+            continue;
+        }
+        let history = query_score_history(
+            client,
+            *version,
+            &plays(profile_id).eq("music_id", play.music_id.as_str()),
+            None,
+        )
+        .await?
+        .into_iter()
+        .filter(|p| p.timestamp <= subscription.last_seen)
+        .collect();
+        earlier.insert(&play.music_id, history);
+        time::sleep(REQUEST_DELAY).await;
+    }
+
+    let mut last_seen = None;
+    for play in new_plays.iter() {
+        let history = earlier.entry(&play.music_id).or_default();
+        let previous = best_by_chart(history).remove(&play.chart_id);
+        if is_personal_best(play, previous.as_ref()) {
+            let text = match personal_best_message(
+                client,
+                cache,
+                *version,
+                dj_name,
+                play,
+                previous.as_ref(),
+            )
+            .await
+            {
+                Ok(text) => text,
+                Err(e) => {
+                    log::warn!("Failed to render the personal best of {}: {}", user, e);
+                    break;
+                }
+            };
+            let mut retries = 0;
+            let sent = loop {
+                match bot
+                    .send_message(subscription.chat_id, text.clone())
+                    .parse_mode(ParseMode::MarkdownV2)
+                    .await
+                {
+                    Err(RequestError::RetryAfter(delay)) if retries < MAX_SEND_RETRIES => {
+                        retries += 1;
+                        time::sleep(delay.duration()).await;
+                    }
+                    sent => break sent,
+                }
+            };
+            match sent {
+                Ok(_) => time::sleep(SEND_DELAY).await,
+                Err(e) if is_unreachable(&e) => {
+                    log::info!("Unsubscribing {} from unreachable chat: {}", user, e);
+                    links.unsubscribe(user).await;
+                    return Ok(None);
+                }
+                // the play and those after it are tried again on the next poll
+                Err(e) => {
+                    log::warn!("Failed to notify personal best of {}: {}", user, e);
+                    break;
+                }
+            }
+        }
+        history.push(play.clone());
+        last_seen = Some(play.timestamp.clone());
+    }
+
+    Ok(last_seen)
+}
+
+/// Poll the plays of subscribed users for new personal bests every interval
+pub async fn run(
+    bot: Bot,
+    client: ArcanaClient,
+    cache: MetadataCache,
+    links: Links,
+    interval: Duration,
+) {
+    let mut ticker = time::interval(interval);
+    loop {
+        ticker.tick().await;
+        // users are polled one by one to spread the requests to Arcana
+        for (user, link) in links.subscribed().await {
+            let Some(subscription) = &link.notify else {
+                continue;
+            };
+            match notify(&bot, &client, &cache, &links, user, &link, subscription).await {
+                Ok(Some(last_seen)) => links.seen(user, &last_seen).await,
+                Ok(None) => (),
+                Err(e) => log::warn!("Failed to notify personal bests of {}: {}", user, e),
+            }
+        }
+        cache.flush().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(ex_score: u32, lamp: Lamp, raised: bool) -> ScoreHistory {
+        ScoreHistory {
+            id: "Vn3kS8pXq2L".to_owned(),
+            chart_id: "8bWq2LkPz0x".to_owned(),
+            ex_score,
+            lamp,
+            miss_count: None,
+            music_id: "G6vGmV2XC2Y".to_owned(),
+            profile_id: "C3PttzgAx6F".to_owned(),
+            raised,
+            status: lamp,
+            timestamp: "2021-10-03T13:01:52Z".to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_is_personal_best() {
+        let previous = best_by_chart(&[play(2650, Lamp::HardClear, false)])
+            .remove("8bWq2LkPz0x")
+            .unwrap();
+        assert!(is_personal_best(
+            &play(2741, Lamp::Clear, false),
+            Some(&previous)
+        ));
+        assert!(is_personal_best(
+            &play(2600, Lamp::ExHardClear, false),
+            Some(&previous)
+        ));
+        assert!(!is_personal_best(
+            &play(2650, Lamp::HardClear, false),
+            Some(&previous)
+        ));
+        assert!(is_personal_best(
+            &play(2600, Lamp::Failed, true),
+            Some(&previous)
+        ));
+        assert!(!is_personal_best(&play(1000, Lamp::Failed, false), None));
+
+        let output = render_personal_best(
+            "ORIGIN",
+            "Mind Mapping",
+            "SP ANOTHER 12",
+            &play(2741, Lamp::ExHardClear, true),
+            Some(&previous),
+        );
+        assert!(output.contains("EX Score: 2650 → 2741\nLamp: HARD CLEAR → EX HARD CLEAR"));
+    }
+
+    #[test]
+    fn test_is_unreachable() {
+        assert!(is_unreachable(&RequestError::Api(ApiError::BotBlocked)));
+        assert!(is_unreachable(&RequestError::Api(ApiError::ChatNotFound)));
+        assert!(is_unreachable(&RequestError::Api(ApiError::Unknown(
+            "Forbidden: bot is not a member of the channel chat".to_owned()
+        ))));
+        assert!(!is_unreachable(&RequestError::Api(
+            ApiError::CantParseEntities("Bad Request: can't parse entities".to_owned())
+        )));
+        assert!(!is_unreachable(&RequestError::RetryAfter(
+            teloxide::types::Seconds::from_seconds(5)
+        )));
+    }
+}
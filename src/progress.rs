@@ -0,0 +1,147 @@
+use chrono::{Duration as ChronoDuration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::time;
+
+use crate::{
+    arcana::{
+        iidx::{get_profile_by_id, get_profile_using_id, DanRank, Profile, Score},
+        ArcanaClient, Result,
+    },
+    links::{Link, Links},
+    DEFAULT_IIDX_VERSION,
+};
+
+/// Profile stats of a linked player on a day
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Snapshot {
+    /// Day of the snapshot, e.g. `2021-10-03`
+    pub date: String,
+    pub version: u32,
+    pub sp: Score,
+    pub dp: Score,
+}
+
+impl Snapshot {
+    pub fn new(version: u32, profile: &Profile) -> Self {
+        Self {
+            date: Utc::now().format("%Y-%m-%d").to_string(),
+            version,
+            sp: profile.sp.clone(),
+            dp: profile.dp.clone(),
+        }
+    }
+
+    /// Whether the stats are the same as another snapshot, whenever taken
+    pub fn same_stats(&self, other: &Snapshot) -> bool {
+        self.version == other.version && self.sp == other.sp && self.dp == other.dp
+    }
+}
+
+/// The latest snapshot taken on or before the day, as history only records
+/// changes
+pub fn as_of<'a>(history: &'a [Snapshot], date: &str) -> Option<&'a Snapshot> {
+    history.iter().rev().find(|s| s.date.as_str() <= date)
+}
+
+/// The day `days` days before `today`
+pub fn days_before(today: &str, days: i64) -> Option<String> {
+    let today = NaiveDate::parse_from_str(today, "%Y-%m-%d").ok()?;
+    Some(
+        (today - ChronoDuration::days(days))
+            .format("%Y-%m-%d")
+            .to_string(),
+    )
+}
+
+/// Days on which the dan rank of a play style changed, with the ranks before
+/// and after
+pub fn rank_changes(
+    history: &[Snapshot],
+    score: impl Fn(&Snapshot) -> &Score,
+) -> Vec<(&str, Option<DanRank>, Option<DanRank>)> {
+    history
+        .windows(2)
+        .filter(|w| score(&w[0]).rank != score(&w[1]).rank)
+        .map(|w| {
+            (
+                w[1].date.as_str(),
+                score(&w[0]).rank.clone(),
+                score(&w[1]).rank.clone(),
+            )
+        })
+        .collect()
+}
+
+/// Take snapshots of the linked profiles every interval
+/// Profile of the linked player in the version played now, which is the
+/// default version once they have played it and the linked one until then
+async fn current_profile(client: &ArcanaClient, link: &Link) -> Result<Option<(u32, Profile)>> {
+    let version = *DEFAULT_IIDX_VERSION;
+    if version > link.version {
+        if let Some(profile) = get_profile_using_id(client, version, &link.iidx_id)
+            .await?
+            .pop()
+        {
+            return Ok(Some((version, profile)));
+        }
+    }
+    Ok(get_profile_by_id(client, link.version, &link.profile_id)
+        .await?
+        .map(|profile| (link.version, profile)))
+}
+
+pub async fn run(client: ArcanaClient, links: Links, interval: Duration) {
+    let mut ticker = time::interval(interval);
+    loop {
+        ticker.tick().await;
+        // profiles are fetched one by one to spread the requests to Arcana
+        for (user, link) in links.all().await {
+            match current_profile(&client, &link).await {
+                Ok(Some((version, profile))) => {
+                    links.record(user, Snapshot::new(version, &profile)).await
+                }
+                Ok(None) => log::warn!("Linked profile of {} is gone", user),
+                Err(e) => log::warn!("Failed to take a snapshot of {}: {}", user, e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(date: &str, dj_points: u32, rank: Option<DanRank>) -> Snapshot {
+        let score = |dj_points, rank| Score {
+            dj_points,
+            plays: dj_points / 2,
+            rank,
+        };
+        Snapshot {
+            date: date.to_owned(),
+            version: 28,
+            sp: score(dj_points, rank),
+            dp: score(0, None),
+        }
+    }
+
+    #[test]
+    fn test_history() {
+        let history = [
+            snapshot("2021-08-01", 1200, Some(DanRank::Dan6)),
+            snapshot("2021-09-10", 1400, Some(DanRank::Dan6)),
+            snapshot("2021-09-28", 1532, Some(DanRank::Dan7)),
+        ];
+        assert_eq!(days_before("2021-10-03", 7).unwrap(), "2021-09-26");
+        assert_eq!(as_of(&history, "2021-09-26").unwrap().sp.dj_points, 1400);
+        assert_eq!(as_of(&history, "2021-09-28").unwrap().sp.dj_points, 1532);
+        assert!(as_of(&history, "2021-07-31").is_none());
+        assert_eq!(
+            rank_changes(&history, |s| &s.sp),
+            vec![("2021-09-28", Some(DanRank::Dan6), Some(DanRank::Dan7))]
+        );
+        assert!(rank_changes(&history, |s| &s.dp).is_empty());
+        assert!(history[2].same_stats(&snapshot("2021-10-03", 1532, Some(DanRank::Dan7))));
+    }
+}
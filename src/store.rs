@@ -0,0 +1,102 @@
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    ffi::OsString,
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tokio::{
+    fs,
+    sync::{Mutex, RwLock},
+};
+
+/// JSON file backing in-memory state, which is replaced atomically on save
+#[derive(Debug, Clone)]
+pub struct JsonFile {
+    path: PathBuf,
+    /// Held while saving so that an older state never overwrites a newer one
+    lock: Arc<Mutex<()>>,
+}
+
+impl JsonFile {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_owned(),
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Path next to the file with the suffix appended, e.g. `links.json.tmp`
+    fn sibling(&self, suffix: &str) -> PathBuf {
+        let mut path = OsString::from(self.path.as_os_str());
+        path.push(suffix);
+        path.into()
+    }
+
+    /// Read the file, starting from the default if it does not exist
+    ///
+    /// A file that cannot be decoded is moved aside to `*.corrupt` rather than
+    /// overwritten by the next save, and startup fails if that is not
+    /// possible.
+    pub async fn load<T: DeserializeOwned + Default>(&self) -> T {
+        let data = match fs::read(&self.path).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return T::default(),
+            Err(e) => panic!("Failed to read {}: {}", self.path.display(), e),
+        };
+        match serde_json::from_slice(&data) {
+            Ok(state) => state,
+            Err(e) => {
+                let corrupt = self.sibling(".corrupt");
+                log::error!(
+                    "Failed to decode {}, moving it to {}: {}",
+                    self.path.display(),
+                    corrupt.display(),
+                    e
+                );
+                if let Err(e) = fs::rename(&self.path, &corrupt).await {
+                    panic!("Failed to move {} aside: {}", self.path.display(), e);
+                }
+                T::default()
+            }
+        }
+    }
+
+    /// Write the state to a temporary file and move it over the file, so that
+    /// a crash never leaves a partial file behind
+    pub async fn save<T: Serialize>(&self, state: &RwLock<T>) -> io::Result<()> {
+        let _guard = self.lock.lock().await;
+        let data = serde_json::to_vec(&*state.read().await)?;
+        let tmp = self.sibling(".tmp");
+        fs::write(&tmp, data).await?;
+        fs::rename(&tmp, &self.path).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn test_json_file() {
+        let path = std::env::temp_dir().join(format!("store-{}.json", std::process::id()));
+        let file = JsonFile::new(&path);
+        let state: HashMap<String, u32> = file.load().await;
+        assert!(state.is_empty());
+
+        let state = RwLock::new(HashMap::from([("a".to_owned(), 1)]));
+        file.save(&state).await.unwrap();
+        let loaded: HashMap<String, u32> = file.load().await;
+        assert_eq!(loaded, *state.read().await);
+
+        // a file that cannot be decoded is kept aside instead of overwritten
+        fs::write(&path, "{").await.unwrap();
+        let loaded: HashMap<String, u32> = file.load().await;
+        assert!(loaded.is_empty());
+        let corrupt = file.sibling(".corrupt");
+        assert_eq!(fs::read_to_string(&corrupt).await.unwrap(), "{");
+        assert!(!path.exists());
+        fs::remove_file(corrupt).await.unwrap();
+    }
+}